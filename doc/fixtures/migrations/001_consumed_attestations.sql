@@ -0,0 +1,10 @@
+-- Bandurria
+--
+-- Migration: record consumed challenge attestations
+
+CREATE TABLE `consumed_attestations` (
+  `comment_id` char(36) CHARACTER SET utf8mb4 COLLATE utf8mb4_bin NOT NULL DEFAULT '',
+  `expire_at` char(19) CHARACTER SET utf8mb4 COLLATE utf8mb4_bin NOT NULL DEFAULT '',
+  PRIMARY KEY (`comment_id`),
+  KEY `expire_at` (`expire_at`) USING BTREE
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
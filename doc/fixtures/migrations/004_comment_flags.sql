@@ -0,0 +1,7 @@
+-- Bandurria
+--
+-- Migration: add pinned and highlighted flags to comments
+
+ALTER TABLE `comments`
+  ADD COLUMN `pinned` tinyint(1) NOT NULL DEFAULT '0' AFTER `approved`,
+  ADD COLUMN `highlighted` tinyint(1) NOT NULL DEFAULT '0' AFTER `pinned`;
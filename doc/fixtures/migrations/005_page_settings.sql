@@ -0,0 +1,7 @@
+-- Bandurria
+--
+-- Migration: add comment closing settings to pages
+
+ALTER TABLE `pages`
+  ADD COLUMN `locked` tinyint(1) NOT NULL DEFAULT '0' AFTER `page`,
+  ADD COLUMN `close_after_days` smallint(6) unsigned DEFAULT NULL AFTER `locked`;
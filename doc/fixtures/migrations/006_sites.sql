@@ -0,0 +1,8 @@
+-- Bandurria
+--
+-- Migration: scope pages by site (existing pages belong to the main site)
+
+ALTER TABLE `pages`
+  ADD COLUMN `site` varchar(64) CHARACTER SET utf8mb4 COLLATE utf8mb4_bin NOT NULL DEFAULT 'default' AFTER `id`,
+  DROP INDEX `page`,
+  ADD UNIQUE KEY `site_page` (`site`,`page`) USING BTREE;
@@ -0,0 +1,117 @@
+-- Bandurria
+--
+-- Migration: store timestamps as native DATETIME columns (instead of strings)
+--
+-- Notice: stored timestamps are all UTC, and DATETIME values are not
+--   converted from or to the session time zone, so they are copied as-is.
+--   Values that cannot be parsed (if any) are listed first, so that affected
+--   rows can be reviewed. They then fall back to the creation time of their
+--   page for comments, or to the time of the migration otherwise.
+
+-- Check: list values that cannot be parsed
+
+SELECT 'authors' AS `table`, `id`, `created_at` AS `value`
+  FROM `authors` WHERE IF(`created_at` REGEXP '^[0-9]{4}-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2}$', CAST(`created_at` AS DATETIME), NULL) IS NULL
+UNION ALL
+SELECT 'avatars', `id`, `refresh_at`
+  FROM `avatars` WHERE IF(`refresh_at` REGEXP '^[0-9]{4}-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2}$', CAST(`refresh_at` AS DATETIME), NULL) IS NULL
+UNION ALL
+SELECT 'avatars', `id`, `created_at`
+  FROM `avatars` WHERE IF(`created_at` REGEXP '^[0-9]{4}-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2}$', CAST(`created_at` AS DATETIME), NULL) IS NULL
+UNION ALL
+SELECT 'comments', `id`, `created_at`
+  FROM `comments` WHERE IF(`created_at` REGEXP '^[0-9]{4}-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2}$', CAST(`created_at` AS DATETIME), NULL) IS NULL
+UNION ALL
+SELECT 'consumed_attestations', `comment_id`, `expire_at`
+  FROM `consumed_attestations` WHERE IF(`expire_at` REGEXP '^[0-9]{4}-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2}$', CAST(`expire_at` AS DATETIME), NULL) IS NULL
+UNION ALL
+SELECT 'pages', `id`, `created_at`
+  FROM `pages` WHERE IF(`created_at` REGEXP '^[0-9]{4}-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2}$', CAST(`created_at` AS DATETIME), NULL) IS NULL
+UNION ALL
+SELECT 'reactions', `comment_id`, `created_at`
+  FROM `reactions` WHERE IF(`created_at` REGEXP '^[0-9]{4}-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2}$', CAST(`created_at` AS DATETIME), NULL) IS NULL;
+
+-- Table: authors
+
+ALTER TABLE `authors`
+  ADD COLUMN `created_at_datetime` datetime DEFAULT NULL AFTER `created_at`;
+
+UPDATE `authors`
+  SET `created_at_datetime` = COALESCE(IF(`created_at` REGEXP '^[0-9]{4}-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2}$', CAST(`created_at` AS DATETIME), NULL), UTC_TIMESTAMP());
+
+ALTER TABLE `authors`
+  DROP COLUMN `created_at`,
+  CHANGE `created_at_datetime` `created_at` datetime NOT NULL;
+
+-- Table: avatars
+
+ALTER TABLE `avatars`
+  ADD COLUMN `refresh_at_datetime` datetime DEFAULT NULL AFTER `refresh_at`,
+  ADD COLUMN `created_at_datetime` datetime DEFAULT NULL AFTER `created_at`;
+
+UPDATE `avatars`
+  SET `refresh_at_datetime` = COALESCE(IF(`refresh_at` REGEXP '^[0-9]{4}-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2}$', CAST(`refresh_at` AS DATETIME), NULL), UTC_TIMESTAMP()),
+    `created_at_datetime` = COALESCE(IF(`created_at` REGEXP '^[0-9]{4}-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2}$', CAST(`created_at` AS DATETIME), NULL), UTC_TIMESTAMP());
+
+ALTER TABLE `avatars`
+  DROP COLUMN `refresh_at`,
+  CHANGE `refresh_at_datetime` `refresh_at` datetime NOT NULL,
+  DROP COLUMN `created_at`,
+  CHANGE `created_at_datetime` `created_at` datetime NOT NULL;
+
+-- Table: comments
+
+ALTER TABLE `comments`
+  ADD COLUMN `created_at_datetime` datetime DEFAULT NULL AFTER `created_at`;
+
+UPDATE `comments`
+  LEFT JOIN `pages` ON `pages`.`id` = `comments`.`page_id`
+  SET `comments`.`created_at_datetime` = COALESCE(
+    IF(`comments`.`created_at` REGEXP '^[0-9]{4}-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2}$', CAST(`comments`.`created_at` AS DATETIME), NULL),
+    IF(`pages`.`created_at` REGEXP '^[0-9]{4}-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2}$', CAST(`pages`.`created_at` AS DATETIME), NULL),
+    UTC_TIMESTAMP()
+  );
+
+ALTER TABLE `comments`
+  DROP INDEX `page_id_approved_created_at`,
+  DROP COLUMN `created_at`,
+  CHANGE `created_at_datetime` `created_at` datetime NOT NULL,
+  ADD KEY `page_id_approved_created_at` (`page_id`,`approved`,`created_at`) USING BTREE;
+
+-- Table: consumed_attestations
+
+ALTER TABLE `consumed_attestations`
+  ADD COLUMN `expire_at_datetime` datetime DEFAULT NULL AFTER `expire_at`;
+
+UPDATE `consumed_attestations`
+  SET `expire_at_datetime` = COALESCE(IF(`expire_at` REGEXP '^[0-9]{4}-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2}$', CAST(`expire_at` AS DATETIME), NULL), UTC_TIMESTAMP());
+
+ALTER TABLE `consumed_attestations`
+  DROP INDEX `expire_at`,
+  DROP COLUMN `expire_at`,
+  CHANGE `expire_at_datetime` `expire_at` datetime NOT NULL,
+  ADD KEY `expire_at` (`expire_at`) USING BTREE;
+
+-- Table: pages
+
+ALTER TABLE `pages`
+  ADD COLUMN `created_at_datetime` datetime DEFAULT NULL AFTER `created_at`;
+
+UPDATE `pages`
+  SET `created_at_datetime` = COALESCE(IF(`created_at` REGEXP '^[0-9]{4}-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2}$', CAST(`created_at` AS DATETIME), NULL), UTC_TIMESTAMP());
+
+ALTER TABLE `pages`
+  DROP COLUMN `created_at`,
+  CHANGE `created_at_datetime` `created_at` datetime NOT NULL;
+
+-- Table: reactions
+
+ALTER TABLE `reactions`
+  ADD COLUMN `created_at_datetime` datetime DEFAULT NULL AFTER `created_at`;
+
+UPDATE `reactions`
+  SET `created_at_datetime` = COALESCE(IF(`created_at` REGEXP '^[0-9]{4}-[0-9]{2}-[0-9]{2} [0-9]{2}:[0-9]{2}:[0-9]{2}$', CAST(`created_at` AS DATETIME), NULL), UTC_TIMESTAMP());
+
+ALTER TABLE `reactions`
+  DROP COLUMN `created_at`,
+  CHANGE `created_at_datetime` `created_at` datetime NOT NULL;
@@ -0,0 +1,10 @@
+-- Bandurria
+--
+-- Migration: store the time digests of pending comments were last sent until
+
+CREATE TABLE `digests` (
+  `site` varchar(64) CHARACTER SET utf8mb4 COLLATE utf8mb4_bin NOT NULL DEFAULT '',
+  `frequency` varchar(16) CHARACTER SET utf8mb4 COLLATE utf8mb4_bin NOT NULL DEFAULT '',
+  `digested_until` datetime NOT NULL,
+  PRIMARY KEY (`site`,`frequency`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4 COLLATE=utf8mb4_unicode_ci;
@@ -23,11 +23,11 @@
 
   /* METHODS */
 
-  var request_api = function (action, payload) {
+  var request_api = function (action, payload, method) {
     return fetch(
       options.base_url + "/api/" + action + "/?" + options.page_query,
       {
-        method: "POST",
+        method: method || "POST",
         body: JSON.stringify(payload),
 
         headers: {
@@ -35,16 +35,29 @@
         },
       },
     ).then(function (response) {
-      if (!response.ok) {
-        return Promise.error("API error: " + action);
-      }
+      return response
+        .json()
+        .catch(function () {
+          return {};
+        })
+        .then(function (result) {
+          if (!response.ok) {
+            return Promise.reject(result.reason || "API error: " + action);
+          }
 
-      return response.json();
+          return result;
+        });
     });
   };
 
-  var load_comments = function (options) {
-    fetch(options.base_url + "/page/comments/?" + options.page_query, {
+  var fetch_comments = function (options, cursor) {
+    var query = options.page_query;
+
+    if (cursor) {
+      query += "&" + new URLSearchParams({ cursor: cursor }).toString();
+    }
+
+    return fetch(options.base_url + "/page/comments/?" + query, {
       method: "GET",
 
       headers: {
@@ -63,8 +76,13 @@
         return response.text();
       })
       .then(function (html) {
-        var page = new DOMParser().parseFromString(html, "text/html");
+        return new DOMParser().parseFromString(html, "text/html");
+      });
+  };
 
+  var load_comments = function (options) {
+    fetch_comments(options, null)
+      .then(function (page) {
         inject_page(options, page);
       })
       .catch(function (error) {
@@ -75,27 +93,85 @@
       });
   };
 
+  var load_more_comments = function (options, more, button) {
+    button.disabled = true;
+
+    fetch_comments(options, button.dataset.cursor)
+      .then(function (page) {
+        inject_more(options, more, page);
+      })
+      .catch(function (error) {
+        button.disabled = false;
+
+        console.error("[Bandurria] Could not load more comments", error);
+      });
+  };
+
+  var load_counts = function (options, counters) {
+    var query = new URLSearchParams(options.site_query);
+
+    for (var counter of counters) {
+      query.append("page", counter.dataset.bandurriaCount);
+    }
+
+    fetch(options.base_url + "/api/counts/?" + query.toString(), {
+      method: "GET",
+
+      headers: {
+        Accept: "application/json",
+      },
+    })
+      .then(function (response) {
+        if (!response.ok) {
+          return Promise.reject(response.status + " " + response.statusText);
+        }
+
+        return response.json();
+      })
+      .then(function (result) {
+        for (var counter of counters) {
+          var count = result.data[counter.dataset.bandurriaCount];
+
+          counter.innerText = count ? count.count : 0;
+        }
+      })
+      .catch(function (error) {
+        console.error("[Bandurria] Could not load comment counts", error);
+      });
+  };
+
   var inject_page = function (options, page) {
-    var $ = select(page.body),
-      $$ = select(page.body, true);
+    var $ = select(page.body);
 
     // Read form template
     var form_template = $(".bandurria-template--form");
 
-    // Inject form
-    inject_form(form_template, $(".bandurria-form"));
+    // Inject form (unless comments are closed on page)
+    var form = $(".bandurria-form--main");
+
+    if (form) {
+      inject_form(form_template, form);
+    }
 
     // Bind comment events (if any)
     var comments = $(".bandurria-comments");
 
     if (comments) {
       bind_comment_events(form_template, comments);
+
+      window.addEventListener("hashchange", function () {
+        handle_comment_anchor_change(options.target, false);
+      });
     }
 
-    // Localize datetimes
-    localize_datetimes($$("[data-datetime]"));
+    // Bind more events (if there are more comments to load)
+    bind_more_events(options, $(".bandurria-more"));
+
+    // Inject all page contents (in the language they were rendered in)
+    if (page.documentElement.lang) {
+      options.target.lang = page.documentElement.lang;
+    }
 
-    // Inject all page contents
     while (page.body.firstChild) {
       options.target.appendChild(page.body.removeChild(page.body.firstChild));
     }
@@ -104,11 +180,38 @@
     // Notice: this will auto-detect if an anchor is set on URL upon loading, \
     //   and no nothing otherwise.
     if (comments) {
-      handle_comment_anchor_change(comments, true);
+      handle_comment_anchor_change(options.target, true);
     }
   };
 
-  var show_banner = function (form, name) {
+  var inject_more = function (options, more, page) {
+    var $ = select(page.body);
+
+    // Read form template (from the initially injected page)
+    var form_template = select(options.target)(".bandurria-template--form");
+
+    // Bind comment events (if any)
+    var comments = $(".bandurria-comments");
+
+    if (comments) {
+      bind_comment_events(form_template, comments);
+    }
+
+    // Bind more events (if there are even more comments to load)
+    bind_more_events(options, $(".bandurria-more"));
+
+    // Inject all page contents in place of the current load more button
+    while (page.body.firstChild) {
+      more.parentNode.insertBefore(
+        page.body.removeChild(page.body.firstChild),
+        more,
+      );
+    }
+
+    more.remove();
+  };
+
+  var show_banner = function (form, name, reason) {
     var $ = select(form),
       $$ = select(form, true);
 
@@ -117,7 +220,15 @@
     }
 
     if (name) {
-      $(".bandurria-banner--" + name).style.display = "block";
+      var selector = ".bandurria-banner--" + name;
+
+      // Pick the banner specific to this reason (if any), or the generic one
+      var banner =
+        (/^[a-z_]+$/.test(reason || "") &&
+          $(selector + "[data-reason='" + reason + "']")) ||
+        $(selector + ":not([data-reason])");
+
+      banner.style.display = "block";
     }
   };
 
@@ -158,10 +269,12 @@
 
         show_banner(form, "submitted");
       })
-      .catch(function () {
+      .catch(function (reason) {
+        // Let the user amend their comment (it might have been rejected)
+        select(form)("textarea").disabled = false;
         button.disabled = false;
 
-        show_banner(form, "submiterror");
+        show_banner(form, "submiterror", reason);
       });
   };
 
@@ -172,6 +285,8 @@
       input_name = $("input[name='comment_name']"),
       input_email = $("input[name='comment_email']"),
       input_subscribe = $("input[name='alerts_subscribe']"),
+      input_website = $("input[name='comment_website']"),
+      input_stamp = $("input[name='comment_stamp']"),
       consent = $(".bandurria-consent"),
       identity = $(".bandurria-identity"),
       button = $("button");
@@ -205,6 +320,8 @@
             text: textarea.value,
             reply_to: form.dataset.replyTo || null,
             alerts_subscribe: input_subscribe.checked || false,
+            website: input_website.value,
+            stamp: input_stamp.value,
           });
         } else {
           // Require user to provide their name and email
@@ -228,23 +345,160 @@
   var bind_comment_events = function (form_template, comments) {
     var $$ = select(comments, true);
 
+    bind_reaction_events(comments);
+
     for (var comment_reply of $$(".bandurria-reply")) {
       comment_reply.onclick = function (event) {
-        var form = event.target.parentNode;
+        open_reply_form(form_template, event.target.parentNode);
+      };
+    }
 
-        event.target.remove();
+    for (var comment_quote of $$(".bandurria-quote")) {
+      comment_quote.onclick = function (event) {
+        var form = event.target.parentNode,
+          name = event.target.dataset.name;
 
-        inject_form(form_template, form, true);
+        // Quote the comment text, and mention its author
+        var text = select(form.parentNode)(".bandurria-text").innerText.trim();
+
+        var quote = text
+          .split("\n")
+          .filter(function (line) {
+            return line.trim() ? true : false;
+          })
+          .map(function (line) {
+            return "> " + line;
+          })
+          .join("\n");
+
+        open_reply_form(form_template, form);
+
+        var textarea = select(form)("textarea[name='comment_text']");
+
+        textarea.value = quote + "\n\n@" + name + " ";
+
+        textarea.onkeyup();
+        textarea.focus();
       };
     }
+  };
 
-    window.addEventListener("hashchange", function () {
-      handle_comment_anchor_change(comments, false);
-    });
+  var bind_reaction_events = function (comments) {
+    var reacted = read_storage("reactions") || [];
+
+    for (var reactions of select(comments, true)(".bandurria-reactions")) {
+      for (var reaction of select(reactions, true)(".bandurria-reaction")) {
+        var reaction_key =
+          reactions.dataset.commentId + "/" + reaction.dataset.reaction;
+
+        reaction.classList.toggle(
+          "bandurria-reaction--active",
+          reacted.indexOf(reaction_key) !== -1,
+        );
+
+        reaction.onclick = function (event) {
+          toggle_reaction(
+            event.currentTarget.parentNode.dataset.commentId,
+            event.currentTarget,
+          );
+        };
+      }
+    }
   };
 
-  var handle_comment_anchor_change = function (comments, scroll_to) {
-    var $ = select(comments);
+  var toggle_reaction = function (comment_id, reaction) {
+    var reaction_key = comment_id + "/" + reaction.dataset.reaction,
+      is_active = reaction.classList.contains("bandurria-reaction--active");
+
+    var payload = {
+      comment_id: comment_id,
+      reaction: reaction.dataset.reaction,
+    };
+
+    reaction.disabled = true;
+
+    // Remove reaction, or add it (this requires solving a lighter challenge)
+    var request = is_active
+      ? request_api("reaction", payload, "DELETE")
+      : request_api("reaction/challenge", {})
+          .then(function (challenge) {
+            var data = challenge.data;
+
+            payload.reaction_id = data.reaction_id;
+
+            return mint_challenge_solutions(
+              data.problems,
+              data.difficulty_expect,
+              data.solutions_expect,
+            );
+          })
+          .then(function (solutions) {
+            payload.mints = solutions;
+
+            return request_api("reaction", payload);
+          });
+
+    request
+      .then(function (result) {
+        var reacted = (read_storage("reactions") || []).filter(function (key) {
+          return key !== reaction_key;
+        });
+
+        if (!is_active) {
+          reacted.push(reaction_key);
+        }
+
+        write_storage("reactions", reacted);
+
+        reaction.classList.toggle("bandurria-reaction--active", !is_active);
+
+        select(reaction)(".bandurria-reaction-count").innerText =
+          result.data.count;
+      })
+      .catch(function (error) {
+        console.error("[Bandurria] Failed toggling reaction", error);
+      })
+      .finally(function () {
+        reaction.disabled = false;
+      });
+  };
+
+  var read_storage = function (key) {
+    try {
+      return JSON.parse(localStorage.getItem("bandurria:" + key));
+    } catch (error) {
+      return null;
+    }
+  };
+
+  var write_storage = function (key, value) {
+    try {
+      localStorage.setItem("bandurria:" + key, JSON.stringify(value));
+    } catch (error) {
+      console.warn("[Bandurria] Could not persist: " + key, error);
+    }
+  };
+
+  var open_reply_form = function (form_template, form) {
+    for (var button of select(form, true)("button")) {
+      button.remove();
+    }
+
+    inject_form(form_template, form, true);
+  };
+
+  var bind_more_events = function (options, more) {
+    if (more) {
+      var button = select(more)(".bandurria-more-button");
+
+      button.onclick = function () {
+        load_more_comments(options, more, button);
+      };
+    }
+  };
+
+  var handle_comment_anchor_change = function (container, scroll_to) {
+    var $ = select(container);
 
     if ((location.hash || "").startsWith("#comment-") === true) {
       var anchored_class = "bandurria-comment--anchored";
@@ -273,28 +527,6 @@
     }
   };
 
-  var localize_datetimes = function (datetimes) {
-    if (datetimes.length > 0) {
-      var formatter = new Intl.DateTimeFormat(undefined, {
-        dateStyle: "short",
-        timeStyle: "short",
-      });
-
-      for (var datetime of datetimes) {
-        var datetime_utc = datetime.dataset.datetime || "";
-
-        try {
-          datetime.innerText = formatter.format(new Date(datetime_utc));
-        } catch (error) {
-          console.error(
-            "[Bandurria] Failed localizing datetime: " + datetime_utc,
-            error,
-          );
-        }
-      }
-    }
-  };
-
   var mint_challenge_solutions = function (
     problems,
     difficulty_expect,
@@ -340,16 +572,45 @@
     base_url: embed_script.src.replace(embed_path, ""),
     target: select(document)(embed_script.dataset.bandurriaTarget),
 
-    page_query: new URLSearchParams({
-      page: window.location.pathname,
-    }).toString(),
+    site_query: new URLSearchParams(
+      embed_script.dataset.bandurriaSite
+        ? { site: embed_script.dataset.bandurriaSite }
+        : {},
+    ).toString(),
+
+    page_query: new URLSearchParams(
+      Object.assign(
+        {
+          page: window.location.pathname,
+        },
+        embed_script.dataset.bandurriaSite
+          ? { site: embed_script.dataset.bandurriaSite }
+          : {},
+        embed_script.dataset.bandurriaLang || document.documentElement.lang
+          ? {
+              lang:
+                embed_script.dataset.bandurriaLang ||
+                document.documentElement.lang,
+            }
+          : {},
+        embed_script.dataset.bandurriaSort
+          ? { sort: embed_script.dataset.bandurriaSort }
+          : {},
+      ),
+    ).toString(),
   };
 
   /* INITIALIZE */
 
+  var counters = select(document, true)("[data-bandurria-count]");
+
+  if (counters.length > 0) {
+    load_counts(options, counters);
+  }
+
   if (options.target) {
     load_comments(options);
-  } else {
+  } else if (counters.length === 0) {
     console.error(
       "[Bandurria] Could not initialize: does bandurria-target exist?",
     );
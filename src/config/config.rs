@@ -4,11 +4,16 @@
 // Copyright: 2025, Valerian Saliou <valerian@valeriansaliou.name>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::path::PathBuf;
 
 use super::defaults;
 use crate::helpers::mint::{MintDifficulty, MintSolutions};
+use crate::helpers::notifier::AlertFrequency;
+use crate::helpers::query::{CommentsSort, RepliesSort};
+use crate::helpers::webhook::WebhookEvent;
+use crate::managers::chat::ChatKind;
 
 #[derive(Deserialize)]
 pub struct Config {
@@ -17,10 +22,27 @@ pub struct Config {
     pub database: ConfigDatabase,
     pub email: ConfigEmail,
     pub site: ConfigSite,
+
+    #[serde(default = "defaults::sites")]
+    pub sites: Vec<ConfigSites>,
+
+    #[serde(default)]
+    pub comments: ConfigComments,
     pub security: ConfigSecurity,
     pub antispam: ConfigAntispam,
+    #[serde(default)]
+    pub reactions: ConfigReactions,
     pub avatar: ConfigAvatar,
     pub i18n: ConfigI18N,
+
+    #[serde(default = "defaults::locales")]
+    pub locales: HashMap<String, ConfigI18N>,
+
+    #[serde(default = "defaults::webhooks")]
+    pub webhooks: Vec<ConfigWebhook>,
+
+    #[serde(default = "defaults::chats")]
+    pub chats: Vec<ConfigChat>,
 }
 
 #[derive(Deserialize)]
@@ -52,6 +74,18 @@ pub struct ConfigDatabaseMySQL {
 pub struct ConfigEmail {
     pub smtp: ConfigEmailSMTP,
     pub identity: ConfigEmailIdentity,
+
+    #[serde(default = "defaults::email_admin_alerts")]
+    pub admin_alerts: bool,
+
+    #[serde(default = "defaults::email_admin_alerts_frequency")]
+    pub admin_alerts_frequency: AlertFrequency,
+
+    #[serde(default = "defaults::email_admin_digest_hour")]
+    pub admin_digest_hour: u8,
+
+    #[serde(default = "defaults::email_admins")]
+    pub admins: Vec<ConfigEmailAdmin>,
 }
 
 #[derive(Deserialize)]
@@ -79,8 +113,17 @@ pub struct ConfigEmailIdentity {
     pub from_email: String,
 }
 
+#[derive(Deserialize)]
+pub struct ConfigEmailAdmin {
+    pub email: String,
+    pub frequency: AlertFrequency,
+}
+
 #[derive(Deserialize)]
 pub struct ConfigSite {
+    #[serde(default = "defaults::site_key")]
+    pub key: String,
+
     pub name: String,
     pub site_url: String,
     pub comments_url: String,
@@ -88,15 +131,93 @@ pub struct ConfigSite {
 
     #[serde(default = "defaults::site_show_imprint")]
     pub show_imprint: bool,
+
+    #[serde(default = "defaults::site_locale")]
+    pub locale: String,
+
+    #[serde(default = "defaults::site_timezone")]
+    pub timezone: String,
+
+    #[serde(default = "defaults::site_pages_close_after_days")]
+    pub pages_close_after_days: HashMap<String, u16>,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigSites {
+    pub key: String,
+    pub name: String,
+    pub site_url: String,
+    pub admin_emails: Vec<String>,
+
+    #[serde(default = "defaults::site_show_imprint")]
+    pub show_imprint: bool,
+
+    #[serde(default = "defaults::site_locale")]
+    pub locale: String,
+
+    #[serde(default = "defaults::site_timezone")]
+    pub timezone: String,
+
+    #[serde(default = "defaults::site_pages_close_after_days")]
+    pub pages_close_after_days: HashMap<String, u16>,
+
+    pub antispam: Option<ConfigAntispam>,
+    pub i18n: Option<ConfigI18N>,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigComments {
+    #[serde(default = "defaults::comments_max_depth")]
+    pub max_depth: usize,
+
+    #[serde(default = "defaults::comments_page_size")]
+    pub page_size: usize,
+
+    #[serde(default = "defaults::comments_sort")]
+    pub sort: CommentsSort,
+
+    #[serde(default = "defaults::comments_replies_sort")]
+    pub replies_sort: RepliesSort,
+
+    pub close_after_days: Option<u16>,
+
+    #[serde(default = "defaults::comments_markdown")]
+    pub markdown: bool,
+
+    #[serde(default = "defaults::comments_feed_size")]
+    pub feed_size: usize,
+
+    #[serde(default = "defaults::comments_feed_max_age")]
+    pub feed_max_age: u32,
+}
+
+impl Default for ConfigComments {
+    fn default() -> Self {
+        ConfigComments {
+            max_depth: defaults::comments_max_depth(),
+            page_size: defaults::comments_page_size(),
+            sort: defaults::comments_sort(),
+            replies_sort: defaults::comments_replies_sort(),
+            close_after_days: None,
+            markdown: defaults::comments_markdown(),
+            feed_size: defaults::comments_feed_size(),
+            feed_max_age: defaults::comments_feed_max_age(),
+        }
+    }
 }
 
 #[derive(Deserialize)]
 pub struct ConfigSecurity {
-    #[serde(default = "defaults::security_secret_key")]
-    pub secret_key: String,
+    pub secret_key: Option<String>,
 
     #[serde(default = "defaults::security_check_pages_exist")]
     pub check_pages_exist: bool,
+
+    #[serde(default = "defaults::security_moderation_links_expire_days")]
+    pub moderation_links_expire_days: u16,
+
+    #[serde(default = "defaults::security_public_snapshots")]
+    pub public_snapshots: bool,
 }
 
 #[derive(Deserialize)]
@@ -109,6 +230,59 @@ pub struct ConfigAntispam {
 
     #[serde(default = "defaults::antispam_solutions_require")]
     pub solutions_require: MintSolutions,
+
+    #[serde(default = "defaults::antispam_submit_delay_minimum")]
+    pub submit_delay_minimum: u64,
+
+    #[serde(default = "defaults::antispam_attestation_expire_seconds")]
+    pub attestation_expire_seconds: u64,
+
+    #[serde(default = "defaults::antispam_text_characters_minimum")]
+    pub text_characters_minimum: usize,
+
+    #[serde(default = "defaults::antispam_text_characters_maximum")]
+    pub text_characters_maximum: usize,
+
+    #[serde(default = "defaults::antispam_text_links_maximum")]
+    pub text_links_maximum: usize,
+
+    #[serde(default = "defaults::antispam_banned_words")]
+    pub banned_words: Vec<String>,
+
+    #[serde(default = "defaults::antispam_banned_patterns")]
+    pub banned_patterns: Vec<String>,
+
+    #[serde(default = "defaults::antispam_nojs_form")]
+    pub nojs_form: bool,
+
+    #[serde(default = "defaults::antispam_nojs_submit_delay_minimum")]
+    pub nojs_submit_delay_minimum: u64,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigReactions {
+    #[serde(default = "defaults::reactions_kinds")]
+    pub kinds: Vec<String>,
+
+    #[serde(default = "defaults::reactions_difficulty")]
+    pub difficulty: MintDifficulty,
+
+    #[serde(default = "defaults::reactions_problems_parallel")]
+    pub problems_parallel: MintSolutions,
+
+    #[serde(default = "defaults::reactions_solutions_require")]
+    pub solutions_require: MintSolutions,
+}
+
+impl Default for ConfigReactions {
+    fn default() -> Self {
+        ConfigReactions {
+            kinds: defaults::reactions_kinds(),
+            difficulty: defaults::reactions_difficulty(),
+            problems_parallel: defaults::reactions_problems_parallel(),
+            solutions_require: defaults::reactions_solutions_require(),
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize)]
@@ -140,12 +314,24 @@ pub struct ConfigI18N {
     #[serde(default = "defaults::i18n_button_reply")]
     pub button_reply: String,
 
+    #[serde(default = "defaults::i18n_button_quote")]
+    pub button_quote: String,
+
+    #[serde(default = "defaults::i18n_button_load_more")]
+    pub button_load_more: String,
+
     #[serde(default = "defaults::i18n_label_leave_a_comment")]
     pub label_leave_a_comment: String,
 
     #[serde(default = "defaults::i18n_label_subscribe_replies")]
     pub label_subscribe_replies: String,
 
+    #[serde(default = "defaults::i18n_banner_comments_closed")]
+    pub banner_comments_closed: String,
+
+    #[serde(default = "defaults::i18n_label_pinned")]
+    pub label_pinned: String,
+
     #[serde(default = "defaults::i18n_label_comments_by")]
     pub label_comments_by: String,
 
@@ -163,4 +349,73 @@ pub struct ConfigI18N {
 
     #[serde(default = "defaults::i18n_banner_submiterror")]
     pub banner_submiterror: String,
+
+    #[serde(default = "defaults::i18n_banner_submiterror_text_too_short")]
+    pub banner_submiterror_text_too_short: String,
+
+    #[serde(default = "defaults::i18n_banner_submiterror_text_too_long")]
+    pub banner_submiterror_text_too_long: String,
+
+    #[serde(default = "defaults::i18n_banner_submiterror_links_too_many")]
+    pub banner_submiterror_links_too_many: String,
+
+    #[serde(default = "defaults::i18n_banner_submiterror_text_banned")]
+    pub banner_submiterror_text_banned: String,
+
+    #[serde(default = "defaults::i18n_field_nojs_question")]
+    pub field_nojs_question: String,
+
+    #[serde(default = "defaults::i18n_banner_submiterror_question_wrong")]
+    pub banner_submiterror_question_wrong: String,
+
+    #[serde(default = "defaults::i18n_format_date")]
+    pub format_date: String,
+
+    #[serde(default = "defaults::i18n_format_time")]
+    pub format_time: String,
+
+    #[serde(default = "defaults::i18n_relative_just_now")]
+    pub relative_just_now: String,
+
+    #[serde(default = "defaults::i18n_relative_minute_ago")]
+    pub relative_minute_ago: String,
+
+    #[serde(default = "defaults::i18n_relative_minutes_ago")]
+    pub relative_minutes_ago: String,
+
+    #[serde(default = "defaults::i18n_relative_hour_ago")]
+    pub relative_hour_ago: String,
+
+    #[serde(default = "defaults::i18n_relative_hours_ago")]
+    pub relative_hours_ago: String,
+
+    #[serde(default = "defaults::i18n_relative_day_ago")]
+    pub relative_day_ago: String,
+
+    #[serde(default = "defaults::i18n_relative_days_ago")]
+    pub relative_days_ago: String,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigWebhook {
+    pub url: String,
+    pub secret: String,
+
+    #[serde(default = "defaults::webhook_events")]
+    pub events: Vec<WebhookEvent>,
+
+    #[serde(default = "defaults::webhook_sites")]
+    pub sites: Vec<String>,
+
+    #[serde(default = "defaults::webhook_retries")]
+    pub retries: u8,
+}
+
+#[derive(Deserialize)]
+pub struct ConfigChat {
+    pub kind: ChatKind,
+    pub url: String,
+
+    #[serde(default = "defaults::chat_sites")]
+    pub sites: Vec<String>,
 }
@@ -4,13 +4,15 @@
 // Copyright: 2025, Valerian Saliou <valerian@valeriansaliou.name>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::path::PathBuf;
 
-use hex;
-use uuid::Uuid;
-
+use super::config::{ConfigChat, ConfigEmailAdmin, ConfigI18N, ConfigSites, ConfigWebhook};
 use crate::helpers::mint::{MintDifficulty, MintSolutions};
+use crate::helpers::notifier::AlertFrequency;
+use crate::helpers::query::{CommentsSort, RepliesSort};
+use crate::helpers::webhook::WebhookEvent;
 
 /* [server] */
 
@@ -46,26 +48,94 @@ pub fn email_identity_from_name() -> String {
     "Comments".into()
 }
 
+pub fn email_admin_alerts() -> bool {
+    true
+}
+
+pub fn email_admin_alerts_frequency() -> AlertFrequency {
+    AlertFrequency::Instant
+}
+
+pub fn email_admin_digest_hour() -> u8 {
+    8
+}
+
+pub fn email_admins() -> Vec<ConfigEmailAdmin> {
+    Vec::new()
+}
+
 /* [site] */
 
+pub fn site_key() -> String {
+    "default".to_string()
+}
+
 pub fn site_show_imprint() -> bool {
     true
 }
 
-/* [security] */
+pub fn site_locale() -> String {
+    "en".to_string()
+}
+
+pub fn site_timezone() -> String {
+    "UTC".to_string()
+}
+
+pub fn site_pages_close_after_days() -> HashMap<String, u16> {
+    HashMap::new()
+}
+
+/* [[sites]] */
+
+pub fn sites() -> Vec<ConfigSites> {
+    Vec::new()
+}
+
+/* [comments] */
+
+pub fn comments_max_depth() -> usize {
+    6
+}
+
+pub fn comments_page_size() -> usize {
+    50
+}
+
+pub fn comments_sort() -> CommentsSort {
+    CommentsSort::Newest
+}
+
+pub fn comments_replies_sort() -> RepliesSort {
+    RepliesSort::Oldest
+}
+
+pub fn comments_markdown() -> bool {
+    false
+}
+
+pub fn comments_feed_size() -> usize {
+    50
+}
 
-pub fn security_secret_key() -> String {
-    // While we recommend that the user pass their own secret key in the \
-    //   configuration, thus ensuring that all signed URLs persist across \
-    //   restarts, we still provide a convenient way to start Bandurria with \
-    //   a random UUIDv4-based secret key if none is provided.
-    hex::encode(Uuid::new_v4().as_bytes())
+pub fn comments_feed_max_age() -> u32 {
+    600
 }
 
+/* [security] */
+
 pub fn security_check_pages_exist() -> bool {
     false
 }
 
+pub fn security_moderation_links_expire_days() -> u16 {
+    14
+}
+
+pub fn security_public_snapshots() -> bool {
+    false
+}
+
 /* [antispam] */
 
 pub fn antispam_difficulty() -> MintDifficulty {
@@ -80,6 +150,60 @@ pub fn antispam_solutions_require() -> MintSolutions {
     6
 }
 
+pub fn antispam_submit_delay_minimum() -> u64 {
+    1
+}
+
+pub fn antispam_attestation_expire_seconds() -> u64 {
+    300
+}
+
+pub fn antispam_text_characters_minimum() -> usize {
+    1
+}
+
+pub fn antispam_text_characters_maximum() -> usize {
+    10000
+}
+
+pub fn antispam_text_links_maximum() -> usize {
+    10
+}
+
+pub fn antispam_banned_words() -> Vec<String> {
+    Vec::new()
+}
+
+pub fn antispam_banned_patterns() -> Vec<String> {
+    Vec::new()
+}
+
+pub fn antispam_nojs_form() -> bool {
+    false
+}
+
+pub fn antispam_nojs_submit_delay_minimum() -> u64 {
+    5
+}
+
+/* [reactions] */
+
+pub fn reactions_kinds() -> Vec<String> {
+    vec!["👍".into()]
+}
+
+pub fn reactions_difficulty() -> MintDifficulty {
+    12
+}
+
+pub fn reactions_problems_parallel() -> MintSolutions {
+    4
+}
+
+pub fn reactions_solutions_require() -> MintSolutions {
+    2
+}
+
 /* [avatar] */
 
 pub fn avatar_gravatar() -> bool {
@@ -116,12 +240,28 @@ pub fn i18n_button_reply() -> String {
     "Reply".into()
 }
 
+pub fn i18n_button_quote() -> String {
+    "Quote".into()
+}
+
+pub fn i18n_button_load_more() -> String {
+    "Load more comments".into()
+}
+
 pub fn i18n_label_leave_a_comment() -> String {
     "Leave a comment:".into()
 }
 
 pub fn i18n_label_subscribe_replies() -> String {
-    "I want to get notified over email when the site owner replies.".into()
+    "I want to get notified over email when the site owner replies or someone mentions me.".into()
+}
+
+pub fn i18n_banner_comments_closed() -> String {
+    "Comments are closed on this page.".into()
+}
+
+pub fn i18n_label_pinned() -> String {
+    "Pinned".into()
 }
 
 pub fn i18n_label_comments_by() -> String {
@@ -147,3 +287,97 @@ pub fn i18n_banner_submitted_notice() -> String {
 pub fn i18n_banner_submiterror() -> String {
     "Your comment could not be submitted. Mind try again?".into()
 }
+
+pub fn i18n_banner_submiterror_text_too_short() -> String {
+    "Your comment is too short. Mind write a bit more?".into()
+}
+
+pub fn i18n_banner_submiterror_text_too_long() -> String {
+    "Your comment is too long. Mind make it shorter?".into()
+}
+
+pub fn i18n_banner_submiterror_links_too_many() -> String {
+    "Your comment contains too many links. Mind remove some?".into()
+}
+
+pub fn i18n_banner_submiterror_text_banned() -> String {
+    "Your comment contains words that are not allowed here.".into()
+}
+
+pub fn i18n_field_nojs_question() -> String {
+    "To prove you are not a bot, how much is {question}?".into()
+}
+
+pub fn i18n_banner_submiterror_question_wrong() -> String {
+    "Your answer to the question is wrong. Mind try again?".into()
+}
+
+pub fn i18n_format_date() -> String {
+    "%Y-%m-%d".into()
+}
+
+pub fn i18n_format_time() -> String {
+    "%H:%M".into()
+}
+
+pub fn i18n_relative_just_now() -> String {
+    "just now".into()
+}
+
+pub fn i18n_relative_minute_ago() -> String {
+    "1 minute ago".into()
+}
+
+pub fn i18n_relative_minutes_ago() -> String {
+    "{count} minutes ago".into()
+}
+
+pub fn i18n_relative_hour_ago() -> String {
+    "1 hour ago".into()
+}
+
+pub fn i18n_relative_hours_ago() -> String {
+    "{count} hours ago".into()
+}
+
+pub fn i18n_relative_day_ago() -> String {
+    "1 day ago".into()
+}
+
+pub fn i18n_relative_days_ago() -> String {
+    "{count} days ago".into()
+}
+
+/* [locales] */
+
+pub fn locales() -> HashMap<String, ConfigI18N> {
+    HashMap::new()
+}
+
+/* [[webhooks]] */
+
+pub fn webhooks() -> Vec<ConfigWebhook> {
+    Vec::new()
+}
+
+pub fn webhook_events() -> Vec<WebhookEvent> {
+    WebhookEvent::all()
+}
+
+pub fn webhook_sites() -> Vec<String> {
+    Vec::new()
+}
+
+pub fn webhook_retries() -> u8 {
+    3
+}
+
+/* [[chats]] */
+
+pub fn chats() -> Vec<ConfigChat> {
+    Vec::new()
+}
+
+pub fn chat_sites() -> Vec<String> {
+    Vec::new()
+}
@@ -7,7 +7,8 @@
 use std::{collections::HashMap, env, fs};
 
 use envsubst::substitute;
-use toml;
+use serde::Deserialize;
+use toml::{self, Table, Value};
 
 use super::config::*;
 use crate::APP_ARGS;
@@ -29,6 +30,32 @@ impl ConfigReader {
         conf = substitute(&conf, &environment).expect("cannot substitute environment variables");
 
         // Parse configuration
-        toml::from_str(&conf).expect("syntax error in config file")
+        let mut conf = conf.parse::<Table>().expect("syntax error in config file");
+
+        Self::merge_sites_antispam(&mut conf);
+
+        Config::deserialize(conf).expect("invalid config file")
+    }
+
+    fn merge_sites_antispam(conf: &mut Table) {
+        // Notice: other sites that provide their own antispam settings inherit \
+        //   the settings of the main site that they do not override (instead \
+        //   of the built-in defaults, which would silently loosen them).
+        let antispam = match conf.get("antispam") {
+            Some(Value::Table(antispam)) => antispam.to_owned(),
+            _ => return,
+        };
+
+        if let Some(Value::Array(sites)) = conf.get_mut("sites") {
+            for site in sites.iter_mut() {
+                if let Some(Value::Table(site_antispam)) = site.get_mut("antispam") {
+                    for (key, value) in antispam.iter() {
+                        if !site_antispam.contains_key(key) {
+                            site_antispam.insert(key.to_owned(), value.to_owned());
+                        }
+                    }
+                }
+            }
+        }
     }
 }
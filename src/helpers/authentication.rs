@@ -4,32 +4,99 @@
 // Copyright: 2025, Valerian Saliou <valerian@valeriansaliou.name>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::ops::Deref;
+
 use hex;
 use hmac::{Hmac, Mac};
 use rocket::http::Status;
 use sha2::Sha256;
 
 use super::normalize;
+use super::site::{self, Site};
+use super::time::{self, Timestamp};
 use crate::APP_CONF;
 
 type HmacSha256 = Hmac<Sha256>;
 
+const SECRET_KEY_LENGTH_MINIMUM: usize = 32;
+const SECRET_KEY_GENERATE_BYTES: usize = 32;
+
 lazy_static! {
-    static ref ADMIN_EMAIL_HASHES: Vec<String> = APP_CONF
-        .site
-        .admin_emails
+    static ref SECRET_KEY: Vec<u8> = make_secret_key();
+    static ref ADMIN_EMAIL_HASHES: HashMap<&'static str, Vec<String>> = site::all()
         .iter()
-        .map(|admin_email| normalize::email_hash(&admin_email))
+        .map(|site| {
+            (
+                site.key,
+                site.admin_emails
+                    .iter()
+                    .map(|admin_email| normalize::email_hash(admin_email))
+                    .collect(),
+            )
+        })
         .collect();
 }
 
-pub fn check_email_hash_is_admin(email_hash: &String) -> bool {
-    ADMIN_EMAIL_HASHES.contains(email_hash)
+fn make_secret_key() -> Vec<u8> {
+    // Important: refuse to start without a strong enough secret key, since \
+    //   all signed payloads (eg. moderation links) would be either \
+    //   forgeable or not persist across restarts otherwise.
+    let secret_key = APP_CONF
+        .security
+        .secret_key
+        .as_ref()
+        .expect("missing security.secret_key (generate one with: bandurria generate-secret)");
+
+    if secret_key.len() < SECRET_KEY_LENGTH_MINIMUM {
+        panic!(
+            "security.secret_key is too short (expected at least {} characters)",
+            SECRET_KEY_LENGTH_MINIMUM
+        );
+    }
+
+    if !secret_key
+        .chars()
+        .all(|character| character.is_ascii_hexdigit())
+    {
+        panic!(
+            "security.secret_key is not hexadecimal (generate one with: bandurria generate-secret)"
+        );
+    }
+
+    secret_key.as_bytes().to_vec()
+}
+
+pub fn ensure_states() {
+    // Ensure all statics are valid (a `deref` is enough to lazily initialize them)
+    let (_, _) = (SECRET_KEY.deref(), ADMIN_EMAIL_HASHES.deref());
+}
+
+pub fn generate_secret_key() -> Result<String, ()> {
+    let mut secret_key = [0; SECRET_KEY_GENERATE_BYTES];
+
+    rustls::crypto::ring::default_provider()
+        .secure_random
+        .fill(&mut secret_key)
+        .or(Err(()))?;
+
+    Ok(hex::encode(secret_key))
+}
+
+pub fn check_email_hash_is_admin(site: &Site, email_hash: &String) -> bool {
+    ADMIN_EMAIL_HASHES
+        .get(site.key)
+        .map(|admin_email_hashes| admin_email_hashes.contains(email_hash))
+        .unwrap_or(false)
 }
 
 pub fn sign_payload_bytes(payload: &str) -> Result<Vec<u8>, ()> {
-    let mut hmac =
-        HmacSha256::new_from_slice(APP_CONF.security.secret_key.as_bytes()).or(Err(()))?;
+    sign_payload_bytes_with_key(&SECRET_KEY, payload)
+}
+
+pub fn sign_payload_bytes_with_key(key: &[u8], payload: &str) -> Result<Vec<u8>, ()> {
+    let mut hmac = HmacSha256::new_from_slice(key).or(Err(()))?;
 
     hmac.update(payload.as_bytes());
 
@@ -40,29 +107,241 @@ pub fn sign_payload(payload: &str) -> Result<String, ()> {
     Ok(hex::encode(sign_payload_bytes(payload)?))
 }
 
-pub fn generate_challenge_attestation(page: &str, comment_id: &str) -> Result<String, Status> {
-    let page_url = normalize::page_url(page)?;
+pub fn verify_payload_bytes(payload: &str, signature: &[u8]) -> bool {
+    if let Ok(mut hmac) = HmacSha256::new_from_slice(&SECRET_KEY) {
+        hmac.update(payload.as_bytes());
 
-    return sign_payload(&format!("{page_url}/{comment_id}")).or(Err(Status::UnprocessableEntity));
+        // Notice: this compares signatures in constant time, which prevents \
+        //   timing attacks that would guess valid signatures byte per byte.
+        hmac.verify_slice(signature).is_ok()
+    } else {
+        false
+    }
 }
 
-pub fn verify_challenge_attestation(page: &str, comment_id: &str, attestation: &str) -> bool {
-    if let Ok(reference_attestation) = generate_challenge_attestation(page, comment_id) {
-        reference_attestation == attestation
+pub fn verify_payload(payload: &str, signature: &str) -> bool {
+    if let Ok(signature_bytes) = hex::decode(signature) {
+        verify_payload_bytes(payload, &signature_bytes)
     } else {
         false
     }
 }
 
-pub fn generate_admin_comment_signature(action: &str, comment_id: &str) -> Result<String, Status> {
-    return sign_payload(&format!("admin/{action}/{comment_id}"))
-        .or(Err(Status::UnprocessableEntity));
+pub fn generate_challenge_attestation(
+    site: &Site,
+    page: &str,
+    comment_id: &str,
+    issued_at: Timestamp,
+    expire_at: Timestamp,
+) -> Result<String, Status> {
+    let payload = challenge_attestation_payload(site, page, comment_id, issued_at, expire_at)?;
+
+    sign_attestation(&payload, issued_at, expire_at)
+}
+
+pub fn verify_challenge_attestation(
+    site: &Site,
+    page: &str,
+    comment_id: &str,
+    attestation: &str,
+) -> Option<(Timestamp, Timestamp)> {
+    verify_attestation(comment_id, attestation, |issued_at, expire_at| {
+        challenge_attestation_payload(site, page, comment_id, issued_at, expire_at)
+    })
+}
+
+pub fn generate_form_attestation(
+    site: &Site,
+    page: &str,
+    comment_id: &str,
+    issued_at: Timestamp,
+    expire_at: Timestamp,
+) -> Result<String, Status> {
+    let payload = form_attestation_payload(site, page, comment_id, issued_at, expire_at)?;
+
+    sign_attestation(&payload, issued_at, expire_at)
+}
+
+pub fn verify_form_attestation(
+    site: &Site,
+    page: &str,
+    comment_id: &str,
+    attestation: &str,
+) -> Option<(Timestamp, Timestamp)> {
+    verify_attestation(comment_id, attestation, |issued_at, expire_at| {
+        form_attestation_payload(site, page, comment_id, issued_at, expire_at)
+    })
+}
+
+pub fn generate_form_answer_signature(comment_id: &str, answer: u8) -> Result<String, Status> {
+    // Notice: the answer to the question is never sent to the reader, \
+    //   instead it is signed along with the comment ID, meaning that the \
+    //   signature only matches if the reader submitted the right answer.
+    sign_payload(&form_answer_payload(comment_id, answer)).or(Err(Status::UnprocessableEntity))
+}
+
+pub fn verify_form_answer_signature(comment_id: &str, answer: u8, signature: &str) -> bool {
+    verify_payload(&form_answer_payload(comment_id, answer), signature)
 }
 
-pub fn verify_admin_comment_signature(action: &str, comment_id: &str, signature: &str) -> bool {
-    if let Ok(reference_signature) = generate_admin_comment_signature(action, comment_id) {
-        reference_signature == signature
+pub fn generate_form_stamp(
+    site: &Site,
+    page: &str,
+    issued_at: Timestamp,
+    expire_at: Timestamp,
+) -> Result<String, Status> {
+    let payload = form_stamp_payload(site, page, issued_at, expire_at)?;
+
+    sign_attestation(&payload, issued_at, expire_at)
+}
+
+pub fn verify_form_stamp(site: &Site, page: &str, stamp: &str) -> Option<(Timestamp, Timestamp)> {
+    verify_attestation(page, stamp, |issued_at, expire_at| {
+        form_stamp_payload(site, page, issued_at, expire_at)
+    })
+}
+
+pub fn generate_admin_comment_signature(
+    action: &str,
+    comment_id: &str,
+    expire_at: Timestamp,
+) -> Result<String, Status> {
+    sign_payload(&admin_comment_payload(action, comment_id, expire_at))
+        .or(Err(Status::UnprocessableEntity))
+}
+
+pub fn verify_admin_comment_signature(
+    action: &str,
+    comment_id: &str,
+    expire_at: Timestamp,
+    signature: &str,
+) -> bool {
+    // Signature has expired? (do not even check it)
+    if time::now_timestamp() >= expire_at {
+        warn!("got expired admin signature for comment: {comment_id}");
+
+        return false;
+    }
+
+    verify_payload(
+        &admin_comment_payload(action, comment_id, expire_at),
+        signature,
+    )
+}
+
+pub fn generate_reaction_client_hash(
+    client_ip: &IpAddr,
+    comment_id: &str,
+) -> Result<String, Status> {
+    // Notice: reactions are deduplicated per client IP address (which the \
+    //   client cannot pick, unlike an identifier), per comment. IP addresses \
+    //   are never stored in clear, only their signature is.
+    sign_payload(&reaction_client_payload(client_ip, comment_id))
+        .or(Err(Status::UnprocessableEntity))
+}
+
+fn sign_attestation(
+    payload: &str,
+    issued_at: Timestamp,
+    expire_at: Timestamp,
+) -> Result<String, Status> {
+    // Notice: the issue and expire times are carried in clear next to the \
+    //   signature, so that they can be checked upon verification, while the \
+    //   signature guarantees that they were not tampered with.
+    let signature = sign_payload(payload).or(Err(Status::UnprocessableEntity))?;
+
+    Ok(format!("{issued_at}:{expire_at}:{signature}"))
+}
+
+fn verify_attestation<F>(
+    subject: &str,
+    attestation: &str,
+    make_payload: F,
+) -> Option<(Timestamp, Timestamp)>
+where
+    F: FnOnce(Timestamp, Timestamp) -> Result<String, Status>,
+{
+    let mut attestation_parts = attestation.split(':');
+
+    let (issued_at, expire_at, signature) = (
+        attestation_parts.next()?.parse::<Timestamp>().ok()?,
+        attestation_parts.next()?.parse::<Timestamp>().ok()?,
+        attestation_parts.next()?,
+    );
+
+    // Attestation has expired? (do not even check its signature)
+    if time::now_timestamp() >= expire_at {
+        warn!("got expired attestation for: {subject}");
+
+        return None;
+    }
+
+    let payload = make_payload(issued_at, expire_at).ok()?;
+
+    if verify_payload(&payload, signature) {
+        Some((issued_at, expire_at))
     } else {
-        false
+        None
     }
 }
+
+fn challenge_attestation_payload(
+    site: &Site,
+    page: &str,
+    comment_id: &str,
+    issued_at: Timestamp,
+    expire_at: Timestamp,
+) -> Result<String, Status> {
+    let page_url = normalize::page_url(page)?;
+
+    // Notice: the site key is signed along with the page, so that an \
+    //   attestation issued for a page cannot be spent on the same page path \
+    //   from another site.
+    Ok(format!(
+        "{}{page_url}/{comment_id}/{issued_at}/{expire_at}",
+        site.key
+    ))
+}
+
+fn form_attestation_payload(
+    site: &Site,
+    page: &str,
+    comment_id: &str,
+    issued_at: Timestamp,
+    expire_at: Timestamp,
+) -> Result<String, Status> {
+    // Notice: form attestations are signed over a distinct payload, so that \
+    //   they cannot be spent as challenge attestations (skipping the mints).
+    Ok(format!(
+        "form/{}",
+        challenge_attestation_payload(site, page, comment_id, issued_at, expire_at)?
+    ))
+}
+
+fn form_answer_payload(comment_id: &str, answer: u8) -> String {
+    format!("form/answer/{comment_id}/{answer}")
+}
+
+fn form_stamp_payload(
+    site: &Site,
+    page: &str,
+    issued_at: Timestamp,
+    expire_at: Timestamp,
+) -> Result<String, Status> {
+    let page_url = normalize::page_url(page)?;
+
+    // Notice: form stamps only attest of when the form was rendered, and \
+    //   are signed over a distinct payload from attestations.
+    Ok(format!(
+        "stamp/{}{page_url}/{issued_at}/{expire_at}",
+        site.key
+    ))
+}
+
+fn admin_comment_payload(action: &str, comment_id: &str, expire_at: Timestamp) -> String {
+    format!("admin/{action}/{comment_id}/{expire_at}")
+}
+
+fn reaction_client_payload(client_ip: &IpAddr, comment_id: &str) -> String {
+    format!("reaction/{comment_id}/{client_ip}")
+}
@@ -104,22 +104,15 @@ async fn pull_cache(db: &mut DbConn, author_id: &str) -> Result<(Option<Avatar>,
             return Ok((None, CacheStatus::Invalid));
         }
 
-        // Refresh date expired? (consider as expired if none)
-        if let Some(refresh_at) = refresh_at {
-            if Utc::now().naive_utc() >= refresh_at {
-                info!(
-                    "cached avatar refresh date reached, marking stale for: {}",
-                    author_id
-                );
-
-                // Return as 'cache stale' (with stale avatar)
-                return Ok((avatar, CacheStatus::Stale));
-            }
-        } else {
-            warn!(
-                "cached avatar refresh date unknown for: {} (unexpected)",
+        // Refresh date expired?
+        if Utc::now().naive_utc() >= refresh_at {
+            info!(
+                "cached avatar refresh date reached, marking stale for: {}",
                 author_id
             );
+
+            // Return as 'cache stale' (with stale avatar)
+            return Ok((avatar, CacheStatus::Stale));
         }
 
         info!("cached avatar acquired and still valid for: {author_id}");
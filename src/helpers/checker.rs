@@ -8,7 +8,7 @@ use std::time::Duration;
 
 use reqwest::{redirect, Client};
 
-use crate::APP_CONF;
+use super::site::Site;
 
 static HTTP_USER_AGENT: &'static str = concat!(
     env!("CARGO_PKG_NAME"),
@@ -34,8 +34,8 @@ lazy_static! {
         .unwrap();
 }
 
-pub async fn page_url_exists(page_url: &str) -> bool {
-    let site_base_url = &APP_CONF.site.site_url;
+pub async fn page_url_exists(site: &Site, page_url: &str) -> bool {
+    let site_base_url = site.site_url;
     let full_uri = format!("{site_base_url}{page_url}");
 
     debug!("checking that page url exists over http: {full_uri}");
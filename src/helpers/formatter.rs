@@ -7,12 +7,27 @@
 // Code from: https://github.com/robinst/linkify/blob/main/demo/src/lib.rs
 
 use linkify::{LinkFinder, LinkKind};
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
 
-pub fn linkify(text: &str) -> String {
+const MARKDOWN_LINK_SCHEMES: [&str; 3] = ["https://", "http://", "mailto:"];
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Mention {
+    pub name: String,
+    pub comment_id: String,
+}
+
+fn make_link_finder() -> LinkFinder {
     let mut link_finder = LinkFinder::new();
 
     link_finder.url_must_have_scheme(true);
 
+    link_finder
+}
+
+pub fn linkify(text: &str, mentions: &[Mention]) -> String {
+    let link_finder = make_link_finder();
+
     let mut bytes = Vec::new();
 
     for span in link_finder.spans(text) {
@@ -42,13 +57,220 @@ pub fn linkify(text: &str) -> String {
                 bytes.extend_from_slice(b"</a>");
             }
             _ => {
-                escape(span.as_str(), &mut bytes);
+                mentionify(span.as_str(), mentions, &mut bytes);
             }
         }
     }
     String::from_utf8(bytes).expect("Added bytes are all ASCII")
 }
 
+pub fn markdown(text: &str, mentions: &[Mention]) -> String {
+    // Notice: only an allowlisted subset of Markdown gets rendered to HTML \
+    //   (paragraphs, emphasis, code, quotes, lists and links). Raw HTML is \
+    //   always escaped, while other elements fall back to their text.
+    let parser = Parser::new_ext(text, Options::ENABLE_STRIKETHROUGH);
+
+    let mut html = String::new();
+    let (mut links, mut code_depth, mut image_depth) = (Vec::new(), 0, 0);
+
+    for event in parser {
+        match event {
+            Event::Start(tag) => match tag {
+                Tag::Paragraph | Tag::Heading { .. } => html.push_str("<p>"),
+                Tag::BlockQuote(_) => html.push_str("<blockquote>"),
+                Tag::CodeBlock(kind) => {
+                    code_depth += 1;
+
+                    match kind {
+                        CodeBlockKind::Fenced(language) if !language.is_empty() => {
+                            html.push_str("<pre><code class=\"language-");
+                            html.push_str(&escape_string(&language));
+                            html.push_str("\">");
+                        }
+                        _ => html.push_str("<pre><code>"),
+                    }
+                }
+                Tag::List(Some(1)) => html.push_str("<ol>"),
+                Tag::List(Some(start)) => html.push_str(&format!("<ol start=\"{}\">", start)),
+                Tag::List(None) => html.push_str("<ul>"),
+                Tag::Item => html.push_str("<li>"),
+                Tag::Emphasis => html.push_str("<em>"),
+                Tag::Strong => html.push_str("<strong>"),
+                Tag::Strikethrough => html.push_str("<del>"),
+                Tag::Link { dest_url, .. } => {
+                    // Only link to allowed schemes (relative and script URLs are dropped)
+                    let is_allowed = image_depth == 0
+                        && MARKDOWN_LINK_SCHEMES
+                            .iter()
+                            .any(|scheme| dest_url.to_lowercase().starts_with(scheme));
+
+                    if is_allowed {
+                        html.push_str("<a href=\"");
+                        html.push_str(&escape_string(&dest_url));
+                        html.push_str("\">");
+                    }
+
+                    links.push(is_allowed);
+                }
+                Tag::Image { .. } => image_depth += 1,
+                _ => {}
+            },
+            Event::End(tag) => match tag {
+                TagEnd::Paragraph | TagEnd::Heading(_) => html.push_str("</p>"),
+                TagEnd::BlockQuote(_) => html.push_str("</blockquote>"),
+                TagEnd::CodeBlock => {
+                    code_depth -= 1;
+
+                    html.push_str("</code></pre>");
+                }
+                TagEnd::List(true) => html.push_str("</ol>"),
+                TagEnd::List(false) => html.push_str("</ul>"),
+                TagEnd::Item => html.push_str("</li>"),
+                TagEnd::Emphasis => html.push_str("</em>"),
+                TagEnd::Strong => html.push_str("</strong>"),
+                TagEnd::Strikethrough => html.push_str("</del>"),
+                TagEnd::Link => html.push_str(match links.pop() {
+                    Some(true) => "</a>",
+                    _ => "",
+                }),
+                TagEnd::Image => image_depth -= 1,
+                _ => {}
+            },
+            Event::Text(text) => {
+                // Auto-link URLs in text, unless already within a link or code
+                if code_depth > 0 || image_depth > 0 || links.contains(&true) {
+                    html.push_str(&escape_string(&text));
+                } else {
+                    html.push_str(&linkify(&text, mentions));
+                }
+            }
+            Event::Code(code) => {
+                html.push_str("<code>");
+                html.push_str(&escape_string(&code));
+                html.push_str("</code>");
+            }
+            Event::Html(raw) | Event::InlineHtml(raw) => html.push_str(&escape_string(&raw)),
+            Event::SoftBreak | Event::HardBreak => html.push_str("<br>"),
+            _ => {}
+        }
+    }
+
+    html
+}
+
+pub fn find_mentions(text: &str, candidates: &[Mention]) -> Vec<Mention> {
+    let mut mentions: Vec<Mention> = Vec::new();
+
+    for (index, _) in text.match_indices('@') {
+        if let Some((mention, _)) = match_mention(text, index, candidates) {
+            // Mention each comment once
+            if !mentions
+                .iter()
+                .any(|existing| existing.comment_id == mention.comment_id)
+            {
+                mentions.push(mention.clone());
+            }
+        }
+    }
+
+    mentions
+}
+
+pub fn count_links(text: &str) -> usize {
+    let mut link_finder = make_link_finder();
+
+    // Only count URLs (emails are not considered as links there)
+    link_finder.kinds(&[LinkKind::Url]);
+
+    link_finder.links(text).count()
+}
+
+fn mentionify(text: &str, mentions: &[Mention], output: &mut Vec<u8>) {
+    let mut last_index = 0;
+
+    for (index, _) in text.match_indices('@') {
+        if index < last_index {
+            continue;
+        }
+
+        if let Some((mention, length)) = match_mention(text, index, mentions) {
+            escape(&text[last_index..index], output);
+
+            output.extend_from_slice(b"<a class=\"bandurria-mention\" href=\"#comment-");
+            escape(&mention.comment_id, output);
+
+            output.extend_from_slice(b"\">");
+            escape(&text[index..(index + length)], output);
+
+            output.extend_from_slice(b"</a>");
+
+            last_index = index + length;
+        }
+    }
+
+    escape(&text[last_index..], output);
+}
+
+fn match_mention<'a>(
+    text: &str,
+    index: usize,
+    mentions: &'a [Mention],
+) -> Option<(&'a Mention, usize)> {
+    // Mentions must start a word (eg. not match within an email address)
+    if text[..index]
+        .chars()
+        .next_back()
+        .map(|character| character.is_alphanumeric())
+        .unwrap_or(false)
+    {
+        return None;
+    }
+
+    let after = &text[(index + 1)..];
+
+    // Match the longest author name (names may contain spaces), compared \
+    //   case-insensitively, and followed by a word boundary.
+    mentions
+        .iter()
+        .filter_map(|mention| {
+            let mut after_characters = after.char_indices();
+
+            for name_character in mention.name.chars() {
+                match after_characters.next() {
+                    Some((_, after_character))
+                        if after_character
+                            .to_lowercase()
+                            .eq(name_character.to_lowercase()) => {}
+                    _ => return None,
+                }
+            }
+
+            let (length, next_character) = match after_characters.next() {
+                Some((offset, character)) => (offset, Some(character)),
+                None => (after.len(), None),
+            };
+
+            if mention.name.is_empty()
+                || next_character
+                    .map(|character| character.is_alphanumeric())
+                    .unwrap_or(false)
+            {
+                None
+            } else {
+                Some((mention, length + 1))
+            }
+        })
+        .max_by_key(|(_, length)| *length)
+}
+
+fn escape_string(text: &str) -> String {
+    let mut bytes = Vec::new();
+
+    escape(text, &mut bytes);
+
+    String::from_utf8(bytes).expect("Escaped bytes are valid UTF-8")
+}
+
 fn escape(text: &str, output: &mut Vec<u8>) {
     for character in text.bytes() {
         match character {
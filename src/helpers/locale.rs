@@ -0,0 +1,165 @@
+// Bandurria
+//
+// Lightweight comment system for static websites
+// Copyright: 2025, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ops::Deref;
+
+use rocket::http::hyper::header::ACCEPT_LANGUAGE;
+use rocket::outcome::try_outcome;
+use rocket::request::{FromRequest, Outcome, Request};
+
+use super::site::{self, Site};
+use super::time;
+use crate::config::config::ConfigI18N;
+use crate::APP_CONF;
+
+pub struct Locale {
+    pub key: &'static str,
+    pub i18n: &'static ConfigI18N,
+}
+
+lazy_static! {
+    // Notice: English strings are the default values of all i18n strings, \
+    //   hence the empty English bundle.
+    static ref BUILTIN_LOCALES: Vec<(&'static str, ConfigI18N)> = vec![
+        ("en", make_builtin_locale("")),
+        ("fr", make_builtin_locale(include_str!("../../res/locales/fr.toml"))),
+        ("de", make_builtin_locale(include_str!("../../res/locales/de.toml"))),
+        ("es", make_builtin_locale(include_str!("../../res/locales/es.toml"))),
+    ];
+
+    static ref LOCALES: HashMap<String, (&'static str, &'static ConfigI18N)> = make_locales();
+}
+
+fn make_builtin_locale(bundle: &str) -> ConfigI18N {
+    toml::from_str(bundle).expect("invalid built-in locale")
+}
+
+fn make_locales() -> HashMap<String, (&'static str, &'static ConfigI18N)> {
+    let mut locales = HashMap::new();
+
+    // Locales from the configuration replace built-in locales with the same key
+    for (key, i18n) in BUILTIN_LOCALES.iter() {
+        locales.insert(normalize_tag(key), (*key, i18n));
+    }
+
+    for (key, i18n) in APP_CONF.locales.iter() {
+        locales.insert(normalize_tag(key), (key.as_str(), i18n));
+    }
+
+    // Ensure that all date and time formats are valid, since formatting a \
+    //   datetime with an invalid format would fail upon rendering comments.
+    let site_locales = site::all().iter().map(|site| (site.locale, site.i18n));
+
+    for (key, i18n) in locales.values().copied().chain(site_locales) {
+        if !time::check_datetime_format(&i18n.format_date)
+            || !time::check_datetime_format(&i18n.format_time)
+        {
+            panic!("invalid date or time format in locale: {}", key);
+        }
+    }
+
+    locales
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Locale {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, ()> {
+        let site = try_outcome!(request.guard::<&Site>().await);
+
+        // 1. Select locale from its explicit tag (if any)
+        // Notice: this is sent by the embed script, from its own lang \
+        //   attribute, or from the lang attribute of the page.
+        let explicit_tags = request
+            .query_value::<&str>("lang")
+            .and_then(|tag| tag.ok())
+            .into_iter();
+
+        // 2. Select locale from browser preferred languages (if any)
+        let accept_tags = request
+            .headers()
+            .get_one(ACCEPT_LANGUAGE.as_str())
+            .map(parse_accept_language)
+            .unwrap_or_default()
+            .into_iter();
+
+        Outcome::Success(select(site, explicit_tags.chain(accept_tags)))
+    }
+}
+
+pub fn ensure_states() {
+    // Ensure all statics are valid (a `deref` is enough to lazily initialize them)
+    let (_, _) = (BUILTIN_LOCALES.deref(), LOCALES.deref());
+}
+
+pub fn select<'a>(site: &'static Site, tags: impl Iterator<Item = &'a str>) -> Locale {
+    let site_tag = normalize_tag(site.locale);
+
+    for tag in tags {
+        let tag = normalize_tag(tag);
+
+        // Try the full tag first (eg. 'fr-ca'), then its primary language \
+        //   only (eg. 'fr').
+        let candidates = [Some(tag.as_str()), tag.split_once('-').map(|tag| tag.0)];
+
+        for candidate in candidates.into_iter().flatten() {
+            // Site strings take precedence in the site locale, since they \
+            //   might have been customized for this site.
+            if candidate == site_tag {
+                return site_locale(site);
+            }
+
+            if let Some(&(key, i18n)) = LOCALES.get(candidate) {
+                return Locale { key, i18n };
+            }
+        }
+    }
+
+    // Fallback on site strings
+    site_locale(site)
+}
+
+fn site_locale(site: &'static Site) -> Locale {
+    Locale {
+        key: site.locale,
+        i18n: site.i18n,
+    }
+}
+
+fn parse_accept_language(header: &str) -> Vec<&str> {
+    // Parse languages and their quality value (eg. 'fr-CH, fr;q=0.9, en;q=0.8')
+    let mut languages: Vec<(&str, f32)> = header
+        .split(',')
+        .filter_map(|language| {
+            let mut language_parts = language.split(';');
+
+            let tag = language_parts.next()?.trim();
+            let quality = language_parts
+                .find_map(|part| part.trim().strip_prefix("q="))
+                .and_then(|quality| quality.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            if tag.is_empty() || tag == "*" || quality <= 0.0 {
+                None
+            } else {
+                Some((tag, quality))
+            }
+        })
+        .collect();
+
+    // Order by preference (the sort is stable, so equal preferences keep \
+    //   their order)
+    languages.sort_by(|first, second| second.1.partial_cmp(&first.1).unwrap_or(Ordering::Equal));
+
+    languages.into_iter().map(|language| language.0).collect()
+}
+
+fn normalize_tag(tag: &str) -> String {
+    tag.trim().replace('_', "-").to_lowercase()
+}
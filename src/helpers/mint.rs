@@ -14,6 +14,7 @@ use regex::Regex;
 use sha2::{Digest, Sha256};
 
 use super::authentication;
+use super::site::Site;
 use crate::APP_CONF;
 
 pub type MintSolutions = u8;
@@ -22,7 +23,12 @@ pub type MintDifficulty = u8;
 type MintIndex = u8;
 type MintTimestamp = u64;
 
-const VALIDITY: Duration = Duration::from_secs(300);
+pub enum MintPurpose<'a> {
+    Comment(&'a Site),
+    Reaction,
+}
+
+pub const VALIDITY: Duration = Duration::from_secs(300);
 const ALGORITHM: &'static str = "SHA-256";
 
 lazy_static! {
@@ -30,7 +36,33 @@ lazy_static! {
         Regex::new(r"^H:([0-9]+):([0-9]+):([^:/]+)/([0-9]+):([^:]+):([^:]+):([^:]+)$").unwrap();
 }
 
-pub fn challenge(comment_id: &str) -> Result<(Vec<String>, MintDifficulty, MintSolutions), ()> {
+impl MintPurpose<'_> {
+    fn difficulty(&self) -> MintDifficulty {
+        match self {
+            MintPurpose::Comment(site) => site.antispam.difficulty,
+            MintPurpose::Reaction => APP_CONF.reactions.difficulty,
+        }
+    }
+
+    fn problems_parallel(&self) -> MintSolutions {
+        match self {
+            MintPurpose::Comment(site) => site.antispam.problems_parallel,
+            MintPurpose::Reaction => APP_CONF.reactions.problems_parallel,
+        }
+    }
+
+    fn solutions_require(&self) -> MintSolutions {
+        match self {
+            MintPurpose::Comment(site) => site.antispam.solutions_require,
+            MintPurpose::Reaction => APP_CONF.reactions.solutions_require,
+        }
+    }
+}
+
+pub fn challenge(
+    comment_id: &str,
+    purpose: MintPurpose,
+) -> Result<(Vec<String>, MintDifficulty, MintSolutions), ()> {
     // Generate expire time (the challenge has a validity period)
     let expire_at_time = SystemTime::now() + VALIDITY;
 
@@ -40,11 +72,11 @@ pub fn challenge(comment_id: &str) -> Result<(Vec<String>, MintDifficulty, MintS
         .as_secs();
 
     // Generate all problem strings
-    let mut problems = Vec::with_capacity(APP_CONF.antispam.problems_parallel as usize);
+    let mut problems = Vec::with_capacity(purpose.problems_parallel() as usize);
 
-    for index in 0..APP_CONF.antispam.problems_parallel {
+    for index in 0..purpose.problems_parallel() {
         problems.push(make_problem(
-            APP_CONF.antispam.difficulty,
+            purpose.difficulty(),
             index,
             comment_id,
             expire_at,
@@ -53,14 +85,14 @@ pub fn challenge(comment_id: &str) -> Result<(Vec<String>, MintDifficulty, MintS
 
     info!("generated mint challenge problems: {:?}", problems);
 
-    Ok((
-        problems,
-        APP_CONF.antispam.difficulty,
-        APP_CONF.antispam.solutions_require,
-    ))
+    Ok((problems, purpose.difficulty(), purpose.solutions_require()))
 }
 
-pub fn verify(reference_comment_id: &str, solutions: &[String]) -> Result<bool, ()> {
+pub fn verify(
+    reference_comment_id: &str,
+    solutions: &[String],
+    purpose: MintPurpose,
+) -> Result<bool, ()> {
     let now_timestamp = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .or(Err(()))?
@@ -83,22 +115,10 @@ pub fn verify(reference_comment_id: &str, solutions: &[String]) -> Result<bool,
             // 1.1. Do not append when nonce does not match content
             // Notice: this is a crucial step that guards against vector \
             //   data tampering.
-            match vector_to_nonce(comment_id, difficulty, index, expire_at) {
-                Ok(reference_nonce) => {
-                    if nonce != reference_nonce {
-                        warn!(
-                            "[verify] could not match vector nonces: {} <> {}",
-                            reference_nonce, nonce
-                        );
+            if !verify_vector_nonce(comment_id, difficulty, index, expire_at, nonce) {
+                warn!("[verify] could not match vector nonce: {}", nonce);
 
-                        continue;
-                    }
-                }
-                Err(_) => {
-                    error!("[verify] could not convert vector to nonce");
-
-                    continue;
-                }
+                continue;
             }
 
             // 1.2. Do not append when solution is expired
@@ -159,7 +179,15 @@ pub fn verify(reference_comment_id: &str, solutions: &[String]) -> Result<bool,
             }
 
             // 1.6. Parse difficulty to number and append vector
+            // Notice: the difficulty must not be lower than the one expected \
+            //   for this purpose, so that solutions to lightweight challenges \
+            //   (eg. for reactions) cannot be spent on comments.
             match difficulty.parse::<MintDifficulty>() {
+                Ok(difficulty) if difficulty < purpose.difficulty() => {
+                    warn!("[verify] got solution with too low difficulty: {difficulty}");
+
+                    continue;
+                }
                 Ok(difficulty) => {
                     vectors_to_verify.push((difficulty, solution));
                 }
@@ -198,7 +226,7 @@ pub fn verify(reference_comment_id: &str, solutions: &[String]) -> Result<bool,
     }
 
     // 3. Ensure we have at least SOLUTIONS_REQUIRED verified solutions
-    Ok(verified_solutions >= APP_CONF.antispam.solutions_require)
+    Ok(verified_solutions >= purpose.solutions_require())
 }
 
 fn make_problem(
@@ -275,8 +303,35 @@ fn vector_to_nonce(
     index_str: &str,
     expire_at_str: &str,
 ) -> Result<String, ()> {
-    let nonce_payload = format!("{difficulty_str}>{index_str}>{comment_id}>{expire_at_str}");
+    let nonce_payload =
+        vector_to_nonce_payload(comment_id, difficulty_str, index_str, expire_at_str);
     let nonce_signature = authentication::sign_payload_bytes(&nonce_payload)?;
 
     Ok(base64_url::encode(&nonce_signature))
 }
+
+fn verify_vector_nonce(
+    comment_id: &str,
+    difficulty_str: &str,
+    index_str: &str,
+    expire_at_str: &str,
+    nonce: &str,
+) -> bool {
+    let nonce_payload =
+        vector_to_nonce_payload(comment_id, difficulty_str, index_str, expire_at_str);
+
+    if let Ok(nonce_signature) = base64_url::decode(nonce) {
+        authentication::verify_payload_bytes(&nonce_payload, &nonce_signature)
+    } else {
+        false
+    }
+}
+
+fn vector_to_nonce_payload(
+    comment_id: &str,
+    difficulty_str: &str,
+    index_str: &str,
+    expire_at_str: &str,
+) -> String {
+    format!("{difficulty_str}>{index_str}>{comment_id}>{expire_at_str}")
+}
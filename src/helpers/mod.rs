@@ -8,9 +8,13 @@ pub mod authentication;
 pub mod avatar;
 pub mod checker;
 pub mod formatter;
+pub mod locale;
 pub mod mint;
 pub mod normalize;
 pub mod notifier;
 pub mod query;
+pub mod rules;
+pub mod site;
 pub mod template;
 pub mod time;
+pub mod webhook;
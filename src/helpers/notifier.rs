@@ -4,42 +4,91 @@
 // Copyright: 2025, Valerian Saliou <valerian@valeriansaliou.name>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
-use super::{authentication, query};
+use chrono::{NaiveDateTime, TimeDelta};
+use rocket_db_pools::sqlx::MySqlPool;
+
+use super::query::{self, PendingComment};
+use super::site::{self, Site};
+use super::{authentication, time};
+use crate::managers::chat::{self, ChatMessage};
 use crate::managers::email as mailer;
 use crate::managers::http::DbConn;
 use crate::APP_CONF;
 
+#[derive(Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertFrequency {
+    Instant,
+    Hourly,
+    Daily,
+    Never,
+}
+
+impl AlertFrequency {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AlertFrequency::Instant => "instant",
+            AlertFrequency::Hourly => "hourly",
+            AlertFrequency::Daily => "daily",
+            AlertFrequency::Never => "never",
+        }
+    }
+
+    fn period(&self) -> Option<TimeDelta> {
+        match self {
+            AlertFrequency::Hourly => Some(TimeDelta::hours(1)),
+            AlertFrequency::Daily => Some(TimeDelta::days(1)),
+            _ => None,
+        }
+    }
+}
+
 pub async fn alert_of_new_comment_to_admins(
+    site: &Site,
     comment_id: &str,
     page: &str,
     name: &str,
     email: &str,
     text: &str,
 ) {
-    let site_url = &APP_CONF.site.site_url;
+    let site_url = site.site_url;
 
-    let moderation_url = format!(
-        "{}/api/admin/moderate/{}/",
-        APP_CONF.site.comments_url, comment_id
+    // Generate moderation links
+    let (approve_url, reject_url) = (
+        make_moderation_url(comment_id, "approve"),
+        make_moderation_url(comment_id, "reject"),
     );
 
-    let moderation_signature =
-        authentication::generate_admin_comment_signature("moderate", comment_id)
-            .unwrap_or("".to_string());
+    let (pin_url, highlight_url, lock_url) = (
+        make_moderation_url(comment_id, "pin"),
+        make_moderation_url(comment_id, "highlight"),
+        make_moderation_url(comment_id, "lock"),
+    );
 
-    // Generate moderation links
     let moderation_links = format!(
         r#"You can approve this comment:
 
-✅ {moderation_url}?signature={moderation_signature}&action=approve
+✅ {approve_url}
 
 Or reject it (this will remove the comment):
 
-❌ {moderation_url}?signature={moderation_signature}&action=reject"#
+❌ {reject_url}
+
+You may also pin it to the top of the discussion:
+
+📌 {pin_url}
+
+Or highlight it:
+
+⭐ {highlight_url}
+
+If the discussion gets out of hand, you can lock comments on this page:
+
+🔒 {lock_url}"#
     );
 
     // Generate email contents
-    let email_subject = format!("💬 New comment on {}", APP_CONF.site.name);
+    let email_subject = format!("💬 New comment on {}", site.name);
 
     let email_body = format!(
         r#"{name} ({email}) said:
@@ -53,8 +102,174 @@ Or reject it (this will remove the comment):
 {moderation_links}"#
     );
 
-    // Send emails to all admins
-    for admin_email in APP_CONF.site.admin_emails.iter() {
+    // Send emails to admins that want to be alerted instantly (other admins \
+    //   get alerted of this comment in their next digest)
+    for admin_email in site.admin_emails.iter() {
+        if admin_alert_frequency(admin_email) == AlertFrequency::Instant {
+            mailer::deliver(admin_email, email_subject.to_owned(), email_body.to_owned()).await
+        }
+    }
+
+    // Post to all chats of the site
+    // Notice: chat messages only carry approve and reject links, to keep \
+    //   them short (other moderation links are sent over email).
+    let chat_message = ChatMessage {
+        title: email_subject,
+        intro: format!("{name} ({email}) said:"),
+        text,
+        links: vec![
+            (
+                "View comment",
+                format!("{site_url}{page}#comment-{comment_id}"),
+            ),
+            ("✅ Approve", approve_url),
+            ("❌ Reject", reject_url),
+        ],
+    };
+
+    for chat in APP_CONF.chats.iter() {
+        if chat.sites.is_empty() || chat.sites.iter().any(|key| key == site.key) {
+            chat::deliver(chat, &chat_message).await
+        }
+    }
+}
+
+pub async fn alert_of_pending_comments_digest_to_admins(
+    pool: &MySqlPool,
+    site: &Site,
+    frequency: AlertFrequency,
+    until: &NaiveDateTime,
+) {
+    let period = match frequency.period() {
+        Some(period) => period,
+        None => return,
+    };
+
+    // Resolve admins that want to be alerted with this digest
+    let admin_emails = site
+        .admin_emails
+        .iter()
+        .filter(|admin_email| admin_alert_frequency(admin_email) == frequency)
+        .collect::<Vec<&String>>();
+
+    if admin_emails.is_empty() {
+        return;
+    }
+
+    // Claim the digest window, which starts where the previous digest ended \
+    //   (or a period ago, if no digest was ever sent)
+    // Notice: this makes sure that comments posted while the server was down \
+    //   are not missed, and that concurrent instances do not send duplicates.
+    let default_since = *until - period;
+
+    let since =
+        match query::claim_digest_window(pool, site, frequency.as_str(), &default_since, until)
+            .await
+        {
+            Ok(Some(since)) => since,
+            Ok(None) => {
+                debug!(
+                    "not alerting of pending comments in {} digest for site: {} (already sent)",
+                    frequency.as_str(),
+                    site.key
+                );
+
+                return;
+            }
+            Err(_) => {
+                error!(
+                    "error claiming {} digest for site: {}",
+                    frequency.as_str(),
+                    site.key
+                );
+
+                return;
+            }
+        };
+
+    // List comments posted since the previous digest, that are still \
+    //   pending (comments that got moderated in the meantime are not listed)
+    match query::list_pending_comments_created_between(pool, site, &since, until).await {
+        Ok(comments) if !comments.is_empty() => {
+            info!(
+                "will alert of {} pending comments in {} digest for site: {}",
+                comments.len(),
+                frequency.as_str(),
+                site.key
+            );
+
+            alert_of_pending_comments_digest(site, &admin_emails, frequency, &comments).await;
+        }
+        Ok(_) => {
+            debug!(
+                "not alerting of pending comments in {} digest for site: {} (none)",
+                frequency.as_str(),
+                site.key
+            )
+        }
+        Err(_) => {
+            error!(
+                "error alerting of pending comments in {} digest for site: {}",
+                frequency.as_str(),
+                site.key
+            )
+        }
+    }
+}
+
+async fn alert_of_pending_comments_digest(
+    site: &Site,
+    admin_emails: &[&String],
+    frequency: AlertFrequency,
+    comments: &[PendingComment],
+) {
+    let site_url = site.site_url;
+
+    // Generate email contents
+    let email_subject = format!(
+        "📬 {} new comment{} on {}",
+        comments.len(),
+        if comments.len() == 1 { "" } else { "s" },
+        site.name
+    );
+
+    let mut email_body = format!(
+        "Here is your {} digest of comments awaiting moderation:",
+        frequency.as_str()
+    );
+
+    for comment in comments {
+        let (comment_id, page, text) = (&comment.id, &comment.page, &comment.text);
+
+        // Notice: author emails are only known if they opted-in to alerts.
+        let author = match comment.email {
+            Some(ref email) => format!("{} ({})", comment.name, email),
+            None => comment.name.to_owned(),
+        };
+
+        let (approve_url, reject_url) = (
+            make_moderation_url(comment_id, "approve"),
+            make_moderation_url(comment_id, "reject"),
+        );
+
+        email_body.push_str(&format!(
+            r#"
+
+—
+
+{author} said:
+
+{text}
+
+{site_url}{page}#comment-{comment_id}
+
+✅ Approve: {approve_url}
+❌ Reject: {reject_url}"#
+        ));
+    }
+
+    // Send digest email to admins
+    for admin_email in admin_emails {
         mailer::deliver(admin_email, email_subject.to_owned(), email_body.to_owned()).await
     }
 }
@@ -85,7 +300,11 @@ pub async fn alert_of_reply_comment_from_admin_if_needed(
             let (parent_email, reply_email_hash, reply_name) =
                 (parent_author.1, reply_author.0, reply_author.2);
 
-            let (reply_page, reply_text) = (reply_page_and_text.0, reply_page_and_text.1);
+            let (reply_site, reply_page, reply_text) = (
+                reply_page_and_text.0,
+                reply_page_and_text.1,
+                reply_page_and_text.2,
+            );
 
             debug!(
                 "checking if should alert of reply to email: {:?}, replier: {} ({})",
@@ -96,7 +315,7 @@ pub async fn alert_of_reply_comment_from_admin_if_needed(
             if let Some(parent_email) = parent_email {
                 // Reply author is administrator? (we only want to notify of \
                 //   replies from administrators)
-                if authentication::check_email_hash_is_admin(&reply_email_hash) {
+                if authentication::check_email_hash_is_admin(reply_site, &reply_email_hash) {
                     info!(
                         "will alert of reply comment to: {} (from admin and opted-in)",
                         parent_comment_id
@@ -104,6 +323,7 @@ pub async fn alert_of_reply_comment_from_admin_if_needed(
 
                     // Deliver reply notification email
                     alert_of_reply_comment_from_admin(
+                        reply_site,
                         reply_comment_id,
                         &parent_email,
                         &reply_page,
@@ -134,16 +354,17 @@ pub async fn alert_of_reply_comment_from_admin_if_needed(
 }
 
 async fn alert_of_reply_comment_from_admin(
+    site: &Site,
     reply_comment_id: &str,
     parent_email: &str,
     page: &str,
     reply_name: &str,
     reply_text: &str,
 ) {
-    let site_url = &APP_CONF.site.site_url;
+    let site_url = site.site_url;
 
     // Generate email contents
-    let email_subject = format!("↪️ New reply on {}", APP_CONF.site.name);
+    let email_subject = format!("↪️ New reply on {}", site.name);
 
     let email_body = format!(
         r#"{reply_name} replied to your comment and said:
@@ -161,3 +382,114 @@ async fn alert_of_reply_comment_from_admin(
     )
     .await
 }
+
+pub async fn alert_of_mentions_in_comment_if_needed(db: &mut DbConn, comment_id: &str) {
+    // 1. Resolve emails of mentioned authors (if they opted-in to email alerts)
+    let mentioned_emails_result = query::list_mentioned_author_emails(db, comment_id).await;
+
+    // 2. Resolve comment author (so that authors do not get notified of their \
+    //   own mentions)
+    let author_result = query::resolve_comment_author_email_name(db, comment_id).await;
+
+    // 3. Resolve comment page and text
+    let page_and_text_result = query::resolve_comment_page_and_text(db, comment_id).await;
+
+    match (mentioned_emails_result, author_result, page_and_text_result) {
+        (Ok(mentioned_emails), Ok(Some(author)), Ok(Some(page_and_text))) => {
+            let (author_email, author_name) = (author.1, author.2);
+            let (site, page, text) = (page_and_text.0, page_and_text.1, page_and_text.2);
+
+            for mentioned_email in mentioned_emails {
+                if author_email.as_ref() == Some(&mentioned_email) {
+                    debug!(
+                        "not alerting of mention in comment: {} (self-mention)",
+                        comment_id
+                    );
+                } else {
+                    info!("will alert of mention in comment: {}", comment_id);
+
+                    alert_of_mention_in_comment(
+                        site,
+                        comment_id,
+                        &mentioned_email,
+                        &page,
+                        &author_name,
+                        &text,
+                    )
+                    .await;
+                }
+            }
+        }
+        _ => {
+            error!("error alerting of mentions in comment: {}", comment_id)
+        }
+    }
+}
+
+async fn alert_of_mention_in_comment(
+    site: &Site,
+    comment_id: &str,
+    mentioned_email: &str,
+    page: &str,
+    name: &str,
+    text: &str,
+) {
+    let site_url = site.site_url;
+
+    // Generate email contents
+    let email_subject = format!("📣 New mention on {}", site.name);
+
+    let email_body = format!(
+        r#"{name} mentioned you in a comment and said:
+
+{text}
+
+{site_url}{page}#comment-{comment_id}"#
+    );
+
+    // Send email to mentioned author
+    mailer::deliver(
+        mentioned_email,
+        email_subject.to_owned(),
+        email_body.to_owned(),
+    )
+    .await
+}
+
+pub fn has_digest_admins() -> bool {
+    site::all().iter().any(|site| {
+        site.admin_emails
+            .iter()
+            .any(|admin_email| admin_alert_frequency(admin_email).period().is_some())
+    })
+}
+
+fn admin_alert_frequency(admin_email: &str) -> AlertFrequency {
+    // Notice: disabling admin alerts disables all admin emails, whatever \
+    //   the preference of each admin.
+    if !APP_CONF.email.admin_alerts {
+        return AlertFrequency::Never;
+    }
+
+    APP_CONF
+        .email
+        .admins
+        .iter()
+        .find(|admin| admin.email.eq_ignore_ascii_case(admin_email))
+        .map(|admin| admin.frequency)
+        .unwrap_or(APP_CONF.email.admin_alerts_frequency)
+}
+
+pub fn make_moderation_url(comment_id: &str, action: &str) -> String {
+    // Moderation links are scoped to their action, and expire after a while
+    let expire_at = time::now_timestamp()
+        + APP_CONF.security.moderation_links_expire_days as u64 * 60 * 60 * 24;
+
+    let signature = authentication::generate_admin_comment_signature(action, comment_id, expire_at)
+        .unwrap_or("".to_string());
+
+    format!(
+        "{}/api/admin/moderate/{}/?action={}&expire={}&signature={}",
+        APP_CONF.site.comments_url, comment_id, action, expire_at, signature
+    )
+}
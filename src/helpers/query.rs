@@ -9,37 +9,173 @@ use std::{collections::HashMap, time::Duration};
 use chrono::NaiveDateTime;
 use rocket::http::Status;
 use rocket_db_pools::{sqlx, sqlx::Row};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use super::{
     avatar::{self, AvatarBytesSize, AvatarData, AvatarMIME, AvatarPixelsSize},
-    checker, normalize, time,
+    checker,
+    formatter::Mention,
+    locale::Locale,
+    normalize,
+    site::{self, Site},
+    time,
 };
 use crate::{managers::http::DbConn, APP_CONF};
 
+const PINNED_SCORE_OFFSET: i64 = 1 << 40;
+
 #[derive(Serialize)]
 pub struct Comment {
     pub id: String,
     pub parent_id: Option<String>,
     pub author_id: String,
     pub name: String,
-    pub avatar: String,
+    pub text: String,
     pub lines: Vec<String>,
+    pub mentions: Vec<Mention>,
+    pub reactions: Vec<CommentReaction>,
+    pub pinned: bool,
+    pub highlighted: bool,
     pub datetime: CommentDateTime,
 }
 
+#[derive(Serialize)]
+pub struct CommentReaction {
+    pub reaction: String,
+    pub count: i64,
+}
+
+#[derive(Serialize)]
+pub struct CommentNode {
+    #[serde(flatten)]
+    pub comment: Comment,
+
+    pub depth: usize,
+    pub replies_count: usize,
+    pub replies: Vec<CommentNode>,
+}
+
+pub struct RecentComment {
+    pub id: String,
+    pub page: String,
+    pub name: String,
+    pub text: String,
+    pub created_at: NaiveDateTime,
+}
+
+pub struct PendingComment {
+    pub id: String,
+    pub page: String,
+    pub name: String,
+    pub email: Option<String>,
+    pub text: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentsSort {
+    Newest,
+    Oldest,
+    Replied,
+    Top,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RepliesSort {
+    Oldest,
+    Newest,
+}
+
+pub struct CommentsCursor {
+    pub score: i64,
+    pub created_at: NaiveDateTime,
+    pub id: String,
+}
+
 #[derive(Serialize)]
 pub struct CommentDateTime {
     pub date: String,
     pub time: String,
     pub utc: String,
+    pub relative: Option<String>,
+}
+
+impl CommentDateTime {
+    fn make(datetime: &NaiveDateTime, site: &Site, locale: &Locale) -> Self {
+        let datetime = &Some(*datetime);
+
+        CommentDateTime {
+            date: time::datetime_to_local_string(
+                datetime,
+                &site.timezone,
+                &locale.i18n.format_date,
+            ),
+            time: time::datetime_to_local_string(
+                datetime,
+                &site.timezone,
+                &locale.i18n.format_time,
+            ),
+            utc: time::datetime_to_utc_string(datetime),
+            relative: time::datetime_to_relative_string(datetime, locale.i18n),
+        }
+    }
 }
 
-pub async fn find_page_id(db: &mut DbConn, page: &str) -> Result<Option<String>, Status> {
+impl CommentsSort {
+    pub fn parse(sort: &str) -> Option<Self> {
+        match sort {
+            "newest" => Some(CommentsSort::Newest),
+            "oldest" => Some(CommentsSort::Oldest),
+            "replied" => Some(CommentsSort::Replied),
+            "top" => Some(CommentsSort::Top),
+            _ => None,
+        }
+    }
+}
+
+impl RepliesSort {
+    fn direction_sql(&self) -> &'static str {
+        match self {
+            RepliesSort::Oldest => "ASC",
+            RepliesSort::Newest => "DESC",
+        }
+    }
+}
+
+impl CommentsCursor {
+    pub fn parse(cursor: &str) -> Option<Self> {
+        let cursor = String::from_utf8(base64_url::decode(cursor).ok()?).ok()?;
+
+        let mut cursor_parts = cursor.splitn(3, '/');
+
+        Some(CommentsCursor {
+            score: cursor_parts.next()?.parse().ok()?,
+            created_at: time::parse_plain_datetime_string(cursor_parts.next()?)?,
+            id: cursor_parts.next()?.to_string(),
+        })
+    }
+
+    pub fn encode(&self) -> String {
+        base64_url::encode(&format!(
+            "{}/{}/{}",
+            self.score,
+            time::datetime_to_plain_string(&self.created_at),
+            self.id
+        ))
+    }
+}
+
+pub async fn find_page_id(
+    db: &mut DbConn,
+    site: &Site,
+    page: &str,
+) -> Result<Option<String>, Status> {
     let page_url = normalize::page_url(page)?;
 
-    let page_id = sqlx::query("SELECT id FROM pages WHERE page = ?")
+    let page_id = sqlx::query("SELECT id FROM pages WHERE site = ? AND page = ?")
+        .bind(site.key)
         .bind(page_url)
         .fetch_optional(&mut ***db)
         .await
@@ -53,7 +189,81 @@ pub async fn find_page_id(db: &mut DbConn, page: &str) -> Result<Option<String>,
     Ok(page_id)
 }
 
-pub async fn find_or_create_page_id(db: &mut DbConn, page: &str) -> Result<String, Status> {
+pub async fn check_page_closed(db: &mut DbConn, site: &Site, page: &str) -> Result<bool, Status> {
+    let page_url = normalize::page_url(page)?;
+
+    let page_settings = sqlx::query(
+        "SELECT locked, close_after_days, created_at FROM pages WHERE site = ? AND page = ?",
+    )
+    .bind(site.key)
+    .bind(&page_url)
+    .fetch_optional(&mut ***db)
+    .await
+    .map_err(|err| {
+        error!("failed loading page settings: {}, because: {}", page, err);
+
+        Status::InternalServerError
+    })?;
+
+    // Notice: pages that were never commented are always open, since they \
+    //   do not have any settings yet.
+    if let Some(page_settings) = page_settings {
+        let (locked, close_after_days, created_at): (bool, Option<u16>, NaiveDateTime) = (
+            page_settings.get("locked"),
+            page_settings.get("close_after_days"),
+            page_settings.get("created_at"),
+        );
+
+        if locked {
+            return Ok(true);
+        }
+
+        // Page-specific delay takes precedence over the site delay for the \
+        //   page, which itself takes precedence over the default delay
+        let close_after_days = close_after_days
+            .or_else(|| site.pages_close_after_days.get(&page_url).copied())
+            .or(APP_CONF.comments.close_after_days);
+
+        if let Some(close_after_days) = close_after_days {
+            let close_at = created_at + Duration::from_secs(close_after_days as u64 * 60 * 60 * 24);
+
+            return Ok(time::now_datetime() >= close_at);
+        }
+    }
+
+    Ok(false)
+}
+
+pub async fn update_page_locked_for_comment_id(
+    db: &mut DbConn,
+    comment_id: &str,
+    locked: bool,
+) -> Result<(), Status> {
+    sqlx::query(
+        r#"UPDATE pages INNER JOIN comments ON comments.page_id = pages.id
+            SET pages.locked = ? WHERE comments.id = ?"#,
+    )
+    .bind(locked)
+    .bind(comment_id)
+    .execute(&mut ***db)
+    .await
+    .map_err(|err| {
+        error!(
+            "failed updating page lock for comment: {}, because: {}",
+            comment_id, err
+        );
+
+        Status::InternalServerError
+    })?;
+
+    Ok(())
+}
+
+pub async fn find_or_create_page_id(
+    db: &mut DbConn,
+    site: &Site,
+    page: &str,
+) -> Result<String, Status> {
     let page_url = normalize::page_url(page)?;
 
     // Safety: assert that page URL is non-empty.
@@ -61,7 +271,7 @@ pub async fn find_or_create_page_id(db: &mut DbConn, page: &str) -> Result<Strin
         return Err(Status::BadRequest);
     }
 
-    match find_page_id(db, page).await? {
+    match find_page_id(db, site, page).await? {
         Some(page_id) => Ok(page_id),
         None => {
             // Check that page exists over HTTP first?
@@ -71,7 +281,7 @@ pub async fn find_or_create_page_id(db: &mut DbConn, page: &str) -> Result<Strin
             //   existence).
             if APP_CONF.security.check_pages_exist {
                 // Actual page do not exist over HTTP, short-circuit here
-                if !checker::page_url_exists(&page_url).await {
+                if !checker::page_url_exists(site, &page_url).await {
                     return Err(Status::Gone);
                 }
             }
@@ -80,12 +290,13 @@ pub async fn find_or_create_page_id(db: &mut DbConn, page: &str) -> Result<Strin
             let page_id = Uuid::new_v4().to_string();
 
             sqlx::query(
-                r#"INSERT INTO pages (id, page, created_at)
-                    VALUES (?, ?, ?)"#,
+                r#"INSERT INTO pages (id, site, page, created_at)
+                    VALUES (?, ?, ?, ?)"#,
             )
             .bind(&page_id)
+            .bind(site.key)
             .bind(page_url)
-            .bind(time::now_datetime_string())
+            .bind(time::now_datetime())
             .execute(&mut ***db)
             .await
             .map_err(|err| {
@@ -136,7 +347,7 @@ pub async fn find_or_create_author_id(
             .bind(&author_id)
             .bind(email_hash)
             .bind(name)
-            .bind(time::now_datetime_string())
+            .bind(time::now_datetime())
             .execute(&mut ***db)
             .await
             .map_err(|err| {
@@ -213,6 +424,65 @@ pub async fn check_comment_exists(db: &mut DbConn, comment_id: &str) -> Result<b
     Ok(comment_exists)
 }
 
+pub async fn check_attestation_consumed(db: &mut DbConn, comment_id: &str) -> Result<bool, Status> {
+    let attestation_consumed =
+        sqlx::query("SELECT comment_id FROM consumed_attestations WHERE comment_id = ?")
+            .bind(comment_id)
+            .fetch_optional(&mut ***db)
+            .await
+            .map_err(|err| {
+                error!(
+                    "failed checking if attestation is consumed: {}, because: {}",
+                    comment_id, err
+                );
+
+                Status::InternalServerError
+            })?
+            .is_some();
+
+    Ok(attestation_consumed)
+}
+
+pub async fn consume_attestation(
+    db: &mut DbConn,
+    comment_id: &str,
+    expire_at: time::Timestamp,
+) -> Result<bool, Status> {
+    // Purge expired attestations first (they cannot be replayed anymore, so \
+    //   there is no need to keep them around)
+    sqlx::query("DELETE FROM consumed_attestations WHERE expire_at <= ?")
+        .bind(time::now_datetime())
+        .execute(&mut ***db)
+        .await
+        .map_err(|err| {
+            error!("failed purging consumed attestations, because: {}", err);
+
+            Status::InternalServerError
+        })?;
+
+    // Mark attestation as consumed (if not already consumed)
+    // Notice: if the attestation was already consumed, no row gets inserted, \
+    //   which tells that the attestation is being replayed.
+    let consume_result = sqlx::query(
+        r#"INSERT IGNORE INTO consumed_attestations (comment_id, expire_at)
+            VALUES (?, ?)"#,
+    )
+    .bind(comment_id)
+    .bind(time::timestamp_to_datetime(expire_at))
+    .execute(&mut ***db)
+    .await
+    .map_err(|err| {
+        error!(
+            "failed consuming attestation: {}, because: {}",
+            comment_id, err
+        );
+
+        Status::InternalServerError
+    })?;
+
+    Ok(consume_result.rows_affected() > 0)
+}
+
 pub async fn resolve_comment_status_and_reply_to_id(
     db: &mut DbConn,
     comment_id: &str,
@@ -293,9 +563,9 @@ pub async fn resolve_comment_author_email_name(
 pub async fn resolve_comment_page_and_text(
     db: &mut DbConn,
     comment_id: &str,
-) -> Result<Option<(String, String)>, Status> {
+) -> Result<Option<(&'static Site, String, String)>, Status> {
     let comment_page_and_text = sqlx::query(
-        r#"SELECT pages.page, comments.text
+        r#"SELECT pages.site, pages.page, comments.text
             FROM comments INNER JOIN pages ON pages.id = comments.page_id
             WHERE comments.id = ?"#,
     )
@@ -310,7 +580,17 @@ pub async fn resolve_comment_page_and_text(
 
         Status::InternalServerError
     })?
-    .map(|comment| (comment.get("page"), comment.get("text")));
+    .map(|comment| {
+        let site_key: &str = comment.get("site");
+
+        // Notice: pages from a site that was since removed from the \
+        //   configuration are attributed to the main site.
+        (
+            site::find_by_key(site_key).unwrap_or_else(site::main),
+            comment.get("page"),
+            comment.get("text"),
+        )
+    });
 
     Ok(comment_page_and_text)
 }
@@ -347,79 +627,591 @@ pub async fn resolve_comment_page_id(
     Ok(comment_page_id)
 }
 
+pub async fn count_comments_for_page_urls(
+    db: &mut DbConn,
+    site: &Site,
+    page_urls: &[String],
+) -> Result<HashMap<String, (i64, Option<NaiveDateTime>)>, Status> {
+    if page_urls.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let counts_query_sql = format!(
+        r#"SELECT pages.page, COUNT(comments.id) AS count,
+                MAX(comments.created_at) AS last_created_at
+            FROM pages INNER JOIN comments ON comments.page_id = pages.id
+            WHERE pages.site = ? AND pages.page IN ({}) AND comments.approved = 1
+            GROUP BY pages.page"#,
+        vec!["?"; page_urls.len()].join(", ")
+    );
+
+    let mut counts_query = sqlx::query(&counts_query_sql).bind(site.key);
+
+    for page_url in page_urls {
+        counts_query = counts_query.bind(page_url);
+    }
+
+    let counts = counts_query
+        .fetch_all(&mut ***db)
+        .await
+        .map_err(|err| {
+            error!("failed counting comments, because: {}", err);
+
+            Status::InternalServerError
+        })?
+        .into_iter()
+        .map(|count| {
+            (
+                count.get("page"),
+                (count.get("count"), count.get("last_created_at")),
+            )
+        })
+        .collect();
+
+    Ok(counts)
+}
+
+pub async fn list_comments_for_page(
+    db: &mut DbConn,
+    site: &Site,
+    locale: &Locale,
+    page: &str,
+    sort: Option<&str>,
+    cursor: Option<&str>,
+) -> Result<(Vec<CommentNode>, Option<CommentsCursor>), Status> {
+    // Parse sort order (or use default sort order)
+    let sort = match sort {
+        Some(sort) => &CommentsSort::parse(sort).ok_or(Status::BadRequest)?,
+        None => &APP_CONF.comments.sort,
+    };
+
+    // Parse cursor (if loading more comments)
+    let cursor = match cursor {
+        Some(cursor) => Some(CommentsCursor::parse(cursor).ok_or(Status::BadRequest)?),
+        None => None,
+    };
+
+    let page_size = Some(APP_CONF.comments.page_size.max(1));
+
+    match find_page_id(db, site, page).await? {
+        Some(page_id) => {
+            list_comments_for_page_id(db, site, locale, &page_id, sort, page_size, cursor.as_ref())
+                .await
+        }
+        None => Ok((Vec::new(), None)),
+    }
+}
+
+pub async fn list_all_comments_for_page(
+    db: &mut DbConn,
+    site: &Site,
+    locale: &Locale,
+    page: &str,
+    sort: Option<&str>,
+) -> Result<Vec<CommentNode>, Status> {
+    // Parse sort order (or use default sort order)
+    let sort = match sort {
+        Some(sort) => &CommentsSort::parse(sort).ok_or(Status::BadRequest)?,
+        None => &APP_CONF.comments.sort,
+    };
+
+    // Notice: all threads are listed at once, in a single query (ie. not \
+    //   paginated), which is used to render snapshots of whole pages.
+    match find_page_id(db, site, page).await? {
+        Some(page_id) => {
+            let (comments, _) =
+                list_comments_for_page_id(db, site, locale, &page_id, sort, None, None).await?;
+
+            Ok(comments)
+        }
+        None => Ok(Vec::new()),
+    }
+}
+
 pub async fn list_comments_for_page_id(
     db: &mut DbConn,
+    site: &Site,
+    locale: &Locale,
     page_id: &str,
-) -> Result<(Vec<Comment>, HashMap<String, Vec<String>>), Status> {
-    let comments: Vec<Comment> = sqlx::query(
-        r#"SELECT comments.id, comments.text, comments.created_at,
-                comments.reply_to_id,
-                authors.id as author_id, authors.name, authors.email_hash
-            FROM comments INNER JOIN authors ON authors.id = comments.author_id
-            WHERE comments.page_id = ? AND comments.approved = 1
-            ORDER BY comments.created_at DESC"#,
-    )
-    .bind(page_id)
-    .fetch_all(&mut ***db)
-    .await
-    .map_err(|err| {
-        error!("failed loading comments: {}", err);
+    sort: &CommentsSort,
+    page_size: Option<usize>,
+    cursor: Option<&CommentsCursor>,
+) -> Result<(Vec<CommentNode>, Option<CommentsCursor>), Status> {
+    // 1. List top-level threads (after cursor, if any)
+    // Notice: threads are ordered by score first (which depends on the sort \
+    //   order, eg. replies count), then by date. Fetch one more thread than \
+    //   the page size, so that we know whether there are more threads to \
+    //   load after this page (all threads are fetched if there is no page size).
+    let (score_sql, direction_sql, comparator_sql) = match sort {
+        CommentsSort::Newest => ("CAST(0 AS SIGNED)", "DESC", "<"),
+        CommentsSort::Oldest => ("CAST(0 AS SIGNED)", "ASC", ">"),
+        CommentsSort::Replied => (
+            r#"(SELECT COUNT(*) FROM comments AS replies
+                WHERE replies.reply_to_id = comments.id AND replies.approved = 1)"#,
+            "DESC",
+            "<",
+        ),
+        CommentsSort::Top => (
+            r#"(SELECT COUNT(*) FROM reactions
+                WHERE reactions.comment_id = comments.id)"#,
+            "DESC",
+            "<",
+        ),
+    };
+
+    // Notice: pinned threads always come first, which is achieved by \
+    //   offsetting their score, so that cursors keep working as-is.
+    let threads_query_sql = format!(
+        r#"SELECT id, created_at, score FROM (
+                SELECT comments.id, comments.created_at,
+                    CAST(comments.pinned * {PINNED_SCORE_OFFSET} + {score_sql} AS SIGNED) AS score
+                FROM comments
+                WHERE comments.page_id = ? AND comments.approved = 1
+                    AND comments.reply_to_id IS NULL
+            ) AS threads
+            {}
+            ORDER BY score DESC, created_at {direction_sql}, id {direction_sql}
+            {}"#,
+        if cursor.is_some() {
+            format!(
+                r#"WHERE score < ? OR (score = ? AND (
+                    created_at {comparator_sql} ?
+                        OR (created_at = ? AND id {comparator_sql} ?)
+                ))"#
+            )
+        } else {
+            "".to_string()
+        },
+        if page_size.is_some() { "LIMIT ?" } else { "" }
+    );
+
+    let mut threads_query = sqlx::query(&threads_query_sql).bind(page_id);
+
+    if let Some(cursor) = cursor {
+        threads_query = threads_query
+            .bind(cursor.score)
+            .bind(cursor.score)
+            .bind(cursor.created_at)
+            .bind(cursor.created_at)
+            .bind(&cursor.id);
+    }
 
-        Status::InternalServerError
-    })?
-    .into_iter()
-    .map(|comment| {
-        let (text, email_hash, created_at): (&str, &str, &str) = (
-            comment.get("text"),
-            comment.get("email_hash"),
-            comment.get("created_at"),
-        );
+    if let Some(page_size) = page_size {
+        threads_query = threads_query.bind((page_size + 1) as u64);
+    }
 
-        // Parse datetime from string
-        let datetime = time::parse_datetime_string(&created_at);
+    let mut threads: Vec<CommentsCursor> = threads_query
+        .fetch_all(&mut ***db)
+        .await
+        .map_err(|err| {
+            error!("failed loading comment threads: {}", err);
 
-        // Split text into lines
-        let text_lines = text
-            .lines()
-            .filter(|line| !line.is_empty())
-            .map(|line| line.to_string())
-            .collect();
+            Status::InternalServerError
+        })?
+        .into_iter()
+        .map(|thread| CommentsCursor {
+            score: thread.get("score"),
+            created_at: thread.get("created_at"),
+            id: thread.get("id"),
+        })
+        .collect();
+
+    // Notice: the next page starts right after the last thread of this page, \
+    //   which must thus be kept in this page.
+    let next_cursor = match page_size {
+        Some(page_size) if threads.len() > page_size => {
+            threads.truncate(page_size);
+
+            threads.last().map(|thread| CommentsCursor {
+                score: thread.score,
+                created_at: thread.created_at,
+                id: thread.id.to_owned(),
+            })
+        }
+        _ => None,
+    };
 
-        Comment {
-            id: comment.get("id"),
-            parent_id: comment.get("reply_to_id"),
-            author_id: comment.get("author_id"),
-            name: comment.get("name"),
-            avatar: email_hash.to_lowercase(),
-            lines: text_lines,
-            datetime: CommentDateTime {
-                date: time::datetime_to_date_string(&datetime),
-                time: time::datetime_to_time_string(&datetime),
-                utc: time::datetime_to_utc_string(&datetime),
-            },
+    if threads.is_empty() {
+        return Ok((Vec::new(), None));
+    }
+
+    // 2. List all comments in threads (including all nested replies)
+    let comments_query_sql = format!(
+        r#"WITH RECURSIVE threads (id) AS (
+                SELECT id FROM comments WHERE id IN ({})
+                UNION ALL
+                SELECT comments.id FROM comments
+                    INNER JOIN threads ON comments.reply_to_id = threads.id
+                    WHERE comments.approved = 1
+            )
+            SELECT comments.id, comments.text, comments.created_at,
+                comments.reply_to_id, comments.pinned, comments.highlighted,
+                authors.id as author_id, authors.name
+            FROM threads
+                INNER JOIN comments ON comments.id = threads.id
+                INNER JOIN authors ON authors.id = comments.author_id
+            ORDER BY comments.pinned DESC, comments.created_at {}, comments.id {}"#,
+        vec!["?"; threads.len()].join(", "),
+        APP_CONF.comments.replies_sort.direction_sql(),
+        APP_CONF.comments.replies_sort.direction_sql(),
+    );
+
+    let mut comments_query = sqlx::query(&comments_query_sql);
+
+    for thread in threads.iter() {
+        comments_query = comments_query.bind(&thread.id);
+    }
+
+    let mut comments: Vec<Comment> = comments_query
+        .fetch_all(&mut ***db)
+        .await
+        .map_err(|err| {
+            error!("failed loading comments: {}", err);
+
+            Status::InternalServerError
+        })?
+        .into_iter()
+        .map(|comment| {
+            let (text, created_at): (&str, NaiveDateTime) =
+                (comment.get("text"), comment.get("created_at"));
+
+            // Split text into lines
+            let text_lines = text
+                .lines()
+                .filter(|line| !line.is_empty())
+                .map(|line| line.to_string())
+                .collect();
+
+            Comment {
+                id: comment.get("id"),
+                parent_id: comment.get("reply_to_id"),
+                author_id: comment.get("author_id"),
+                name: comment.get("name"),
+                text: text.to_string(),
+                lines: text_lines,
+                mentions: Vec::new(),
+                reactions: Vec::new(),
+                pinned: comment.get("pinned"),
+                highlighted: comment.get("highlighted"),
+                datetime: CommentDateTime::make(&created_at, site, locale),
+            }
+        })
+        .collect();
+
+    // 3. Attach mentions and reaction counts to their comments
+    let comment_ids: Vec<&str> = comments.iter().map(|comment| comment.id.as_str()).collect();
+
+    let mut mentions = list_mentions_for_comment_ids(db, &comment_ids).await?;
+    let reactions = count_reactions_for_comment_ids(db, &comment_ids).await?;
+
+    for comment in comments.iter_mut() {
+        if let Some(comment_mentions) = mentions.remove(&comment.id) {
+            comment.mentions = comment_mentions;
         }
-    })
-    .collect();
 
-    // Generate replies references (parent IDs mapping to children IDs)
-    let mut replies: HashMap<String, Vec<String>> = HashMap::new();
+        // Notice: all configured reactions are listed (even if none was \
+        //   added yet), so that they can be added from the comment.
+        comment.reactions = APP_CONF
+            .reactions
+            .kinds
+            .iter()
+            .map(|reaction| CommentReaction {
+                reaction: reaction.to_owned(),
+                count: reactions
+                    .get(&(comment.id.to_owned(), reaction.to_owned()))
+                    .copied()
+                    .unwrap_or(0),
+            })
+            .collect();
+    }
+
+    Ok((
+        build_comment_tree(comments, &threads, APP_CONF.comments.max_depth),
+        next_cursor,
+    ))
+}
 
-    for comment in comments.iter() {
+fn build_comment_tree(
+    comments: Vec<Comment>,
+    threads: &[CommentsCursor],
+    max_depth: usize,
+) -> Vec<CommentNode> {
+    // Generate replies references (parent IDs mapping to children indexes)
+    // Notice: indexes are used so that children keep the order they were \
+    //   listed in, and so that each comment can be moved to its node once.
+    let mut replies: HashMap<String, Vec<usize>> = HashMap::new();
+    let mut roots: Vec<usize> = Vec::new();
+
+    for (index, comment) in comments.iter().enumerate() {
         if let Some(parent_id) = comment.parent_id.as_ref() {
-            let (parent_id_string, id_string) = (parent_id.to_string(), comment.id.to_string());
+            replies
+                .entry(parent_id.to_string())
+                .or_default()
+                .push(index);
+        } else {
+            roots.push(index);
+        }
+    }
 
-            // Insert relationship to replies store
-            let parent_store = replies.get_mut(&parent_id_string);
+    // Order top-level comments in the same order as their threads
+    let threads_order: HashMap<&str, usize> = threads
+        .iter()
+        .enumerate()
+        .map(|(order, thread)| (thread.id.as_str(), order))
+        .collect();
+
+    roots.sort_by_key(|index| threads_order.get(comments[*index].id.as_str()));
+
+    // Build tree nodes, starting from top-level comments
+    // Notice: replies to comments that are not listed (eg. not approved) \
+    //   are never reached, and thus never rendered.
+    let mut comments: Vec<Option<Comment>> = comments.into_iter().map(Some).collect();
+
+    // Notice: top-level comments count as the first nesting level, thus the \
+    //   deepest depth is one less than the maximum depth (though replies \
+    //   always get nested once, even with a maximum depth of one).
+    let deepest_depth = max_depth.saturating_sub(1).max(1);
+
+    roots
+        .into_iter()
+        .filter_map(|index| build_comment_node(index, 0, deepest_depth, &mut comments, &replies))
+        .collect()
+}
 
-            if let Some(parent_store) = parent_store {
-                parent_store.push(id_string);
-            } else {
-                replies.insert(parent_id_string, vec![id_string]);
+fn build_comment_node(
+    index: usize,
+    depth: usize,
+    deepest_depth: usize,
+    comments: &mut Vec<Option<Comment>>,
+    replies: &HashMap<String, Vec<usize>>,
+) -> Option<CommentNode> {
+    let comment = comments[index].take()?;
+
+    let children = replies.get(&comment.id).map(|children| children.as_slice());
+
+    let reply_nodes: Vec<CommentNode> = if depth + 1 < deepest_depth {
+        // Nest replies under this comment
+        children
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|child| {
+                build_comment_node(*child, depth + 1, deepest_depth, comments, replies)
+            })
+            .collect()
+    } else {
+        // Deepest depth reached: flatten all deeper replies as direct replies \
+        //   of this comment (in the order they were listed)
+        let mut descendants = Vec::new();
+
+        collect_comment_descendants(
+            children.unwrap_or_default(),
+            replies,
+            comments,
+            &mut descendants,
+        );
+
+        descendants.sort();
+
+        descendants
+            .into_iter()
+            .filter_map(|descendant| {
+                comments[descendant].take().map(|comment| CommentNode {
+                    comment,
+                    depth: depth + 1,
+                    replies_count: 0,
+                    replies: Vec::new(),
+                })
+            })
+            .collect()
+    };
+
+    Some(CommentNode {
+        comment,
+        depth,
+        replies_count: reply_nodes
+            .iter()
+            .map(|reply_node| 1 + reply_node.replies_count)
+            .sum(),
+        replies: reply_nodes,
+    })
+}
+
+fn collect_comment_descendants(
+    children: &[usize],
+    replies: &HashMap<String, Vec<usize>>,
+    comments: &[Option<Comment>],
+    descendants: &mut Vec<usize>,
+) {
+    for child in children {
+        if let Some(comment) = comments[*child].as_ref() {
+            descendants.push(*child);
+
+            if let Some(grand_children) = replies.get(&comment.id) {
+                collect_comment_descendants(grand_children, replies, comments, descendants);
             }
         }
     }
+}
 
-    Ok((comments, replies))
+pub async fn list_recent_comments(
+    db: &mut DbConn,
+    site: &Site,
+    page_id: Option<&str>,
+    limit: u64,
+) -> Result<Vec<RecentComment>, Status> {
+    let recent_query_sql = format!(
+        r#"SELECT comments.id, comments.text, comments.created_at,
+                pages.page, authors.name
+            FROM comments
+                INNER JOIN pages ON pages.id = comments.page_id
+                INNER JOIN authors ON authors.id = comments.author_id
+            WHERE pages.site = ? AND comments.approved = 1 {}
+            ORDER BY comments.created_at DESC, comments.id DESC
+            LIMIT ?"#,
+        if page_id.is_some() {
+            "AND comments.page_id = ?"
+        } else {
+            ""
+        }
+    );
+
+    let mut recent_query = sqlx::query(&recent_query_sql).bind(site.key);
+
+    if let Some(page_id) = page_id {
+        recent_query = recent_query.bind(page_id);
+    }
+
+    let recent_comments = recent_query
+        .bind(limit)
+        .fetch_all(&mut ***db)
+        .await
+        .map_err(|err| {
+            error!("failed loading recent comments: {}", err);
+
+            Status::InternalServerError
+        })?
+        .into_iter()
+        .map(|comment| RecentComment {
+            id: comment.get("id"),
+            page: comment.get("page"),
+            name: comment.get("name"),
+            text: comment.get("text"),
+            created_at: comment.get("created_at"),
+        })
+        .collect();
+
+    Ok(recent_comments)
+}
+
+pub async fn claim_digest_window(
+    pool: &sqlx::MySqlPool,
+    site: &Site,
+    frequency: &str,
+    default_since: &NaiveDateTime,
+    until: &NaiveDateTime,
+) -> Result<Option<NaiveDateTime>, Status> {
+    // Notice: this runs outside of any request (ie. from the digest \
+    //   scheduler), hence why it queries the pool directly.
+    // Initialize the digest window (if this is the first digest ever sent)
+    sqlx::query(
+        r#"INSERT IGNORE INTO digests (site, frequency, digested_until)
+            VALUES (?, ?, ?)"#,
+    )
+    .bind(site.key)
+    .bind(frequency)
+    .bind(default_since)
+    .execute(pool)
+    .await
+    .map_err(|err| {
+        error!("failed initializing {} digest window: {}", frequency, err);
+
+        Status::InternalServerError
+    })?;
+
+    // Resolve the time the previous digest was sent until
+    let digested_until: NaiveDateTime = sqlx::query(
+        r#"SELECT digested_until FROM digests
+            WHERE site = ? AND frequency = ?"#,
+    )
+    .bind(site.key)
+    .bind(frequency)
+    .fetch_one(pool)
+    .await
+    .map_err(|err| {
+        error!("failed resolving {} digest window: {}", frequency, err);
+
+        Status::InternalServerError
+    })?
+    .get("digested_until");
+
+    if digested_until >= *until {
+        return Ok(None);
+    }
+
+    // Claim the digest window
+    // Notice: the window only gets claimed if it was not moved in the \
+    //   meantime, so that concurrent instances cannot send the same digest.
+    let claim_result = sqlx::query(
+        r#"UPDATE digests SET digested_until = ?
+            WHERE site = ? AND frequency = ? AND digested_until = ?"#,
+    )
+    .bind(until)
+    .bind(site.key)
+    .bind(frequency)
+    .bind(digested_until)
+    .execute(pool)
+    .await
+    .map_err(|err| {
+        error!("failed claiming {} digest window: {}", frequency, err);
+
+        Status::InternalServerError
+    })?;
+
+    if claim_result.rows_affected() > 0 {
+        Ok(Some(digested_until))
+    } else {
+        Ok(None)
+    }
+}
+
+pub async fn list_pending_comments_created_between(
+    pool: &sqlx::MySqlPool,
+    site: &Site,
+    since: &NaiveDateTime,
+    until: &NaiveDateTime,
+) -> Result<Vec<PendingComment>, Status> {
+    // Notice: this runs outside of any request (ie. from the digest \
+    //   scheduler), hence why it queries the pool directly.
+    let pending_comments = sqlx::query(
+        r#"SELECT comments.id, comments.text, pages.page, authors.name, authors.email
+            FROM comments
+                INNER JOIN pages ON pages.id = comments.page_id
+                INNER JOIN authors ON authors.id = comments.author_id
+            WHERE pages.site = ? AND comments.approved = 0
+                AND comments.created_at >= ? AND comments.created_at < ?
+            ORDER BY comments.created_at ASC, comments.id ASC"#,
+    )
+    .bind(site.key)
+    .bind(since)
+    .bind(until)
+    .fetch_all(pool)
+    .await
+    .map_err(|err| {
+        error!("failed loading pending comments: {}", err);
+
+        Status::InternalServerError
+    })?
+    .into_iter()
+    .map(|comment| PendingComment {
+        id: comment.get("id"),
+        page: comment.get("page"),
+        name: comment.get("name"),
+        email: comment.get("email"),
+        text: comment.get("text"),
+    })
+    .collect();
+
+    Ok(pending_comments)
 }
 
 pub async fn insert_comment_for_page_id_and_author_id(
@@ -472,7 +1264,7 @@ pub async fn insert_comment_for_page_id_and_author_id(
     )
     .bind(comment_id)
     .bind(text)
-    .bind(time::now_datetime_string())
+    .bind(time::now_datetime())
     .bind(author_id)
     .bind(page_id)
     .bind(reply_to_id)
@@ -487,10 +1279,258 @@ pub async fn insert_comment_for_page_id_and_author_id(
     Ok(())
 }
 
+pub async fn list_mention_candidates_for_page_id(
+    db: &mut DbConn,
+    page_id: &str,
+) -> Result<Vec<Mention>, Status> {
+    let candidates = sqlx::query(
+        r#"SELECT comments.id, authors.name
+            FROM comments INNER JOIN authors ON authors.id = comments.author_id
+            WHERE comments.page_id = ? AND comments.approved = 1
+            ORDER BY comments.created_at DESC, comments.id DESC"#,
+    )
+    .bind(page_id)
+    .fetch_all(&mut ***db)
+    .await
+    .map_err(|err| {
+        error!(
+            "failed listing mention candidates for page: {}, because: {}",
+            page_id, err
+        );
+
+        Status::InternalServerError
+    })?;
+
+    // Mentions of an author resolve to their most recent comment on the page
+    let mut mentions: Vec<Mention> = Vec::new();
+
+    for candidate in candidates {
+        let name: String = candidate.get("name");
+
+        if !mentions
+            .iter()
+            .any(|mention| mention.name.to_lowercase() == name.to_lowercase())
+        {
+            mentions.push(Mention {
+                name,
+                comment_id: candidate.get("id"),
+            });
+        }
+    }
+
+    Ok(mentions)
+}
+
+pub async fn insert_comment_mentions(
+    db: &mut DbConn,
+    comment_id: &str,
+    mentions: &[Mention],
+) -> Result<(), Status> {
+    for mention in mentions {
+        sqlx::query(
+            r#"INSERT IGNORE INTO comment_mentions (comment_id, mention_id, name)
+                VALUES (?, ?, ?)"#,
+        )
+        .bind(comment_id)
+        .bind(&mention.comment_id)
+        .bind(&mention.name)
+        .execute(&mut ***db)
+        .await
+        .map_err(|err| {
+            error!("failed inserting comment mention: {}", err);
+
+            Status::InternalServerError
+        })?;
+    }
+
+    Ok(())
+}
+
+pub async fn list_mentions_for_comment_ids(
+    db: &mut DbConn,
+    comment_ids: &[&str],
+) -> Result<HashMap<String, Vec<Mention>>, Status> {
+    if comment_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let mentions_query_sql = format!(
+        "SELECT comment_id, mention_id, name FROM comment_mentions WHERE comment_id IN ({})",
+        vec!["?"; comment_ids.len()].join(", ")
+    );
+
+    let mut mentions_query = sqlx::query(&mentions_query_sql);
+
+    for comment_id in comment_ids {
+        mentions_query = mentions_query.bind(comment_id);
+    }
+
+    let mut mentions: HashMap<String, Vec<Mention>> = HashMap::new();
+
+    for mention in mentions_query.fetch_all(&mut ***db).await.map_err(|err| {
+        error!("failed listing comment mentions, because: {}", err);
+
+        Status::InternalServerError
+    })? {
+        mentions
+            .entry(mention.get("comment_id"))
+            .or_default()
+            .push(Mention {
+                name: mention.get("name"),
+                comment_id: mention.get("mention_id"),
+            });
+    }
+
+    Ok(mentions)
+}
+
+pub async fn list_mentioned_author_emails(
+    db: &mut DbConn,
+    comment_id: &str,
+) -> Result<Vec<String>, Status> {
+    let emails = sqlx::query(
+        r#"SELECT DISTINCT authors.email
+            FROM comment_mentions
+                INNER JOIN comments ON comments.id = comment_mentions.mention_id
+                INNER JOIN authors ON authors.id = comments.author_id
+            WHERE comment_mentions.comment_id = ? AND authors.email IS NOT NULL"#,
+    )
+    .bind(comment_id)
+    .fetch_all(&mut ***db)
+    .await
+    .map_err(|err| {
+        error!(
+            "failed listing mentioned authors for comment: {}, because: {}",
+            comment_id, err
+        );
+
+        Status::InternalServerError
+    })?
+    .into_iter()
+    .map(|author| author.get("email"))
+    .collect();
+
+    Ok(emails)
+}
+
+pub async fn count_reactions_for_comment_ids(
+    db: &mut DbConn,
+    comment_ids: &[&str],
+) -> Result<HashMap<(String, String), i64>, Status> {
+    if comment_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let reactions_query_sql = format!(
+        r#"SELECT comment_id, reaction, COUNT(*) AS count FROM reactions
+            WHERE comment_id IN ({})
+            GROUP BY comment_id, reaction"#,
+        vec!["?"; comment_ids.len()].join(", ")
+    );
+
+    let mut reactions_query = sqlx::query(&reactions_query_sql);
+
+    for comment_id in comment_ids {
+        reactions_query = reactions_query.bind(comment_id);
+    }
+
+    let reactions = reactions_query
+        .fetch_all(&mut ***db)
+        .await
+        .map_err(|err| {
+            error!("failed counting comment reactions, because: {}", err);
+
+            Status::InternalServerError
+        })?
+        .into_iter()
+        .map(|reaction| {
+            (
+                (reaction.get("comment_id"), reaction.get("reaction")),
+                reaction.get("count"),
+            )
+        })
+        .collect();
+
+    Ok(reactions)
+}
+
+pub async fn count_reactions_for_comment_id(
+    db: &mut DbConn,
+    comment_id: &str,
+    reaction: &str,
+) -> Result<i64, Status> {
+    let count = sqlx::query(
+        "SELECT COUNT(*) AS count FROM reactions WHERE comment_id = ? AND reaction = ?",
+    )
+    .bind(comment_id)
+    .bind(reaction)
+    .fetch_one(&mut ***db)
+    .await
+    .map_err(|err| {
+        error!(
+            "failed counting reactions for comment: {}, because: {}",
+            comment_id, err
+        );
+
+        Status::InternalServerError
+    })?
+    .get("count");
+
+    Ok(count)
+}
+
+pub async fn insert_reaction(
+    db: &mut DbConn,
+    comment_id: &str,
+    reaction: &str,
+    client_hash: &str,
+) -> Result<(), Status> {
+    // Notice: a client can add each reaction once to a comment, adding the \
+    //   same reaction again has no effect.
+    sqlx::query(
+        r#"INSERT IGNORE INTO reactions (comment_id, reaction, client_hash, created_at)
+            VALUES (?, ?, ?, ?)"#,
+    )
+    .bind(comment_id)
+    .bind(reaction)
+    .bind(client_hash)
+    .bind(time::now_datetime())
+    .execute(&mut ***db)
+    .await
+    .map_err(|err| {
+        error!("failed inserting reaction: {}", err);
+
+        Status::InternalServerError
+    })?;
+
+    Ok(())
+}
+
+pub async fn remove_reaction(
+    db: &mut DbConn,
+    comment_id: &str,
+    reaction: &str,
+    client_hash: &str,
+) -> Result<(), Status> {
+    sqlx::query("DELETE FROM reactions WHERE comment_id = ? AND reaction = ? AND client_hash = ?")
+        .bind(comment_id)
+        .bind(reaction)
+        .bind(client_hash)
+        .execute(&mut ***db)
+        .await
+        .map_err(|err| {
+            error!("failed removing reaction: {}", err);
+
+            Status::InternalServerError
+        })?;
+
+    Ok(())
+}
+
 pub async fn resolve_avatar(
     db: &mut DbConn,
     author_id: &str,
-) -> Result<Option<(avatar::AvatarMaybe, AvatarPixelsSize, Option<NaiveDateTime>)>, Status> {
+) -> Result<Option<(avatar::AvatarMaybe, AvatarPixelsSize, NaiveDateTime)>, Status> {
     let avatar_data = sqlx::query(
         r#"SELECT mime, data, bytes_size, pixels_size, refresh_at
             FROM avatars
@@ -505,20 +1545,14 @@ pub async fn resolve_avatar(
         Status::InternalServerError
     })?
     .map(|avatar| {
-        let (pixels_size, refresh_at): (AvatarPixelsSize, String) =
-            (avatar.get("pixels_size"), avatar.get("refresh_at"));
-
-        // Parse datetime from string
-        let refresh_at_datetime = time::parse_datetime_string(&refresh_at);
-
         (
             avatar::AvatarMaybe {
                 data: avatar.get("data"),
                 mime: avatar.get("mime"),
                 size: avatar.get("bytes_size"),
             },
-            pixels_size,
-            refresh_at_datetime,
+            avatar.get("pixels_size"),
+            avatar.get("refresh_at"),
         )
     });
 
@@ -563,8 +1597,8 @@ pub async fn insert_or_update_avatar(
     .bind(size)
     .bind(&*avatar::AVATAR_SIZE_FULL)
     .bind(author_id)
-    .bind(time::now_after_datetime_string(refresh_after))
-    .bind(time::now_datetime_string())
+    .bind(time::now_after_datetime(refresh_after))
+    .bind(time::now_datetime())
     .execute(&mut ***db)
     .await
     .map_err(|err| {
@@ -575,3 +1609,101 @@ pub async fn insert_or_update_avatar(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_comment(id: &str, parent_id: Option<&str>) -> Comment {
+        Comment {
+            id: id.to_string(),
+            parent_id: parent_id.map(|parent_id| parent_id.to_string()),
+            author_id: "author".to_string(),
+            name: "Author".to_string(),
+            text: String::new(),
+            lines: Vec::new(),
+            mentions: Vec::new(),
+            reactions: Vec::new(),
+            pinned: false,
+            highlighted: false,
+            datetime: CommentDateTime {
+                date: String::new(),
+                time: String::new(),
+                utc: String::new(),
+                relative: None,
+            },
+        }
+    }
+
+    fn make_thread(max_depth: usize) -> Vec<CommentNode> {
+        // Notice: a chain of replies, each replying to the previous comment
+        let comments = vec![
+            make_comment("a", None),
+            make_comment("b", Some("a")),
+            make_comment("c", Some("b")),
+            make_comment("d", Some("c")),
+        ];
+
+        let threads = vec![CommentsCursor {
+            score: 0,
+            created_at: NaiveDateTime::default(),
+            id: "a".to_string(),
+        }];
+
+        build_comment_tree(comments, &threads, max_depth)
+    }
+
+    fn collect_depths(nodes: &[CommentNode], depths: &mut Vec<(String, usize)>) {
+        for node in nodes {
+            depths.push((node.comment.id.to_owned(), node.depth));
+
+            collect_depths(&node.replies, depths);
+        }
+    }
+
+    fn thread_depths(max_depth: usize) -> Vec<(String, usize)> {
+        let mut depths = Vec::new();
+
+        collect_depths(&make_thread(max_depth), &mut depths);
+
+        depths
+    }
+
+    #[test]
+    fn it_nests_replies_up_to_maximum_depth() {
+        assert_eq!(
+            thread_depths(4),
+            vec![
+                ("a".to_string(), 0),
+                ("b".to_string(), 1),
+                ("c".to_string(), 2),
+                ("d".to_string(), 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn it_flattens_replies_at_deepest_depth() {
+        assert_eq!(
+            thread_depths(3),
+            vec![
+                ("a".to_string(), 0),
+                ("b".to_string(), 1),
+                ("c".to_string(), 2),
+                ("d".to_string(), 2)
+            ]
+        );
+
+        assert_eq!(
+            thread_depths(2),
+            vec![
+                ("a".to_string(), 0),
+                ("b".to_string(), 1),
+                ("c".to_string(), 1),
+                ("d".to_string(), 1)
+            ]
+        );
+
+        assert_eq!(thread_depths(1), thread_depths(2));
+    }
+}
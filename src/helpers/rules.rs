@@ -0,0 +1,118 @@
+// Bandurria
+//
+// Lightweight comment system for static websites
+// Copyright: 2025, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Deref;
+
+use regex::bytes::{Regex, RegexBuilder};
+
+use super::formatter;
+use super::site::{self, Site};
+
+pub enum RuleViolation {
+    TextTooShort,
+    TextTooLong,
+    LinksTooMany,
+    TextBanned,
+}
+
+lazy_static! {
+    static ref BANNED_WORDS: HashMap<&'static str, HashSet<String>> = site::all()
+        .iter()
+        .map(|site| {
+            (
+                site.key,
+                site.antispam
+                    .banned_words
+                    .iter()
+                    .map(|banned_word| banned_word.to_lowercase())
+                    .collect(),
+            )
+        })
+        .collect();
+
+    // Notice: patterns are matched against raw bytes with Unicode support \
+    //   disabled, since the 'regex' crate is built without its Unicode \
+    //   features. Matching is case-insensitive over ASCII characters.
+    static ref BANNED_PATTERNS: HashMap<&'static str, Vec<Regex>> = site::all()
+        .iter()
+        .map(|site| {
+            (
+                site.key,
+                site.antispam
+                    .banned_patterns
+                    .iter()
+                    .map(|banned_pattern| {
+                        RegexBuilder::new(banned_pattern)
+                            .unicode(false)
+                            .case_insensitive(true)
+                            .build()
+                            .expect("invalid banned pattern")
+                    })
+                    .collect(),
+            )
+        })
+        .collect();
+}
+
+impl RuleViolation {
+    pub fn reason(&self) -> &'static str {
+        match self {
+            RuleViolation::TextTooShort => "text_too_short",
+            RuleViolation::TextTooLong => "text_too_long",
+            RuleViolation::LinksTooMany => "links_too_many",
+            RuleViolation::TextBanned => "text_banned",
+        }
+    }
+}
+
+pub fn ensure_states() {
+    // Ensure all statics are valid (a `deref` is enough to lazily initialize them)
+    let (_, _) = (BANNED_WORDS.deref(), BANNED_PATTERNS.deref());
+}
+
+pub fn check_text(site: &Site, text: &str) -> Result<(), RuleViolation> {
+    let config = site.antispam;
+
+    // 1. Check text length (in characters, not in bytes)
+    let text_characters = text.chars().count();
+
+    if text_characters < config.text_characters_minimum {
+        return Err(RuleViolation::TextTooShort);
+    }
+    if text_characters > config.text_characters_maximum {
+        return Err(RuleViolation::TextTooLong);
+    }
+
+    // 2. Check links count
+    if formatter::count_links(text) > config.text_links_maximum {
+        return Err(RuleViolation::LinksTooMany);
+    }
+
+    // 3. Check for banned words (matched against whole words only)
+    let banned_words = &BANNED_WORDS[site.key];
+
+    if !banned_words.is_empty() {
+        let has_banned_word = text
+            .to_lowercase()
+            .split(|character: char| !character.is_alphanumeric())
+            .any(|word| banned_words.contains(word));
+
+        if has_banned_word {
+            return Err(RuleViolation::TextBanned);
+        }
+    }
+
+    // 4. Check for banned patterns
+    if BANNED_PATTERNS[site.key]
+        .iter()
+        .any(|banned_pattern| banned_pattern.is_match(text.as_bytes()))
+    {
+        return Err(RuleViolation::TextBanned);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,179 @@
+// Bandurria
+//
+// Lightweight comment system for static websites
+// Copyright: 2025, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Deref;
+use std::str::FromStr;
+
+use chrono_tz::Tz;
+use rocket::http::hyper::header::ORIGIN;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+
+use super::normalize;
+use crate::config::config::{ConfigAntispam, ConfigI18N};
+use crate::APP_CONF;
+
+pub struct Site {
+    pub key: &'static str,
+    pub name: &'static str,
+    pub site_url: &'static str,
+    pub admin_emails: &'static [String],
+    pub show_imprint: bool,
+    pub locale: &'static str,
+    pub timezone: Tz,
+    pub pages_close_after_days: HashMap<String, u16>,
+    pub antispam: &'static ConfigAntispam,
+    pub i18n: &'static ConfigI18N,
+}
+
+lazy_static! {
+    static ref SITES: Vec<Site> = make_sites();
+}
+
+fn make_sites() -> Vec<Site> {
+    // Notice: the main site always comes first, since it is the one used \
+    //   when a request cannot be attributed to any other site.
+    let mut sites = vec![Site {
+        key: &APP_CONF.site.key,
+        name: &APP_CONF.site.name,
+        site_url: &APP_CONF.site.site_url,
+        admin_emails: &APP_CONF.site.admin_emails,
+        show_imprint: APP_CONF.site.show_imprint,
+        locale: &APP_CONF.site.locale,
+        timezone: parse_timezone(&APP_CONF.site.timezone),
+        pages_close_after_days: normalize_pages_close_after_days(
+            &APP_CONF.site.pages_close_after_days,
+        ),
+        antispam: &APP_CONF.antispam,
+        i18n: &APP_CONF.i18n,
+    }];
+
+    // Other sites fallback on the main site antispam and i18n settings, \
+    //   unless they provide their own.
+    for site in APP_CONF.sites.iter() {
+        sites.push(Site {
+            key: &site.key,
+            name: &site.name,
+            site_url: &site.site_url,
+            admin_emails: &site.admin_emails,
+            show_imprint: site.show_imprint,
+            locale: &site.locale,
+            timezone: parse_timezone(&site.timezone),
+            pages_close_after_days: normalize_pages_close_after_days(&site.pages_close_after_days),
+            antispam: site.antispam.as_ref().unwrap_or(&APP_CONF.antispam),
+            i18n: site.i18n.as_ref().unwrap_or(&APP_CONF.i18n),
+        });
+    }
+
+    // Ensure that site keys are valid and unique, since they are stored \
+    //   along with pages in the database.
+    let mut keys = HashSet::new();
+
+    for site in sites.iter() {
+        if site.key.is_empty()
+            || site.key.len() > 64
+            || !site.key.chars().all(|character| {
+                character.is_ascii_alphanumeric() || character == '-' || character == '_'
+            })
+        {
+            panic!("invalid site key: {}", site.key);
+        }
+
+        if !keys.insert(site.key) {
+            panic!("duplicate site key: {}", site.key);
+        }
+    }
+
+    sites
+}
+
+fn parse_timezone(timezone: &str) -> Tz {
+    Tz::from_str(timezone).expect("invalid site timezone (expected eg. Europe/Paris)")
+}
+
+fn normalize_pages_close_after_days(
+    pages_close_after_days: &HashMap<String, u16>,
+) -> HashMap<String, u16> {
+    // Notice: pages are normalized, so that they match the pages stored in \
+    //   the database whatever the way they are written in the configuration.
+    pages_close_after_days
+        .iter()
+        .map(|(page, days)| {
+            (
+                normalize::page_url(page).expect("invalid page in pages_close_after_days"),
+                *days,
+            )
+        })
+        .collect()
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for &'static Site {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, ()> {
+        // 1. Select site from its explicit key (if any)
+        // Notice: an unknown site key is refused, instead of falling back on \
+        //   the main site, as comments would otherwise end up on the wrong site.
+        if let Some(key) = request.query_value::<&str>("site") {
+            return match key.ok().and_then(find_by_key) {
+                Some(site) => Outcome::Success(site),
+                None => Outcome::Error((Status::BadRequest, ())),
+            };
+        }
+
+        // 2. Select site from request origin (if any)
+        if let Some(site) = request
+            .headers()
+            .get_one(ORIGIN.as_str())
+            .and_then(find_by_origin)
+        {
+            return Outcome::Success(site);
+        }
+
+        // 3. Fallback on the main site
+        Outcome::Success(main())
+    }
+}
+
+pub fn ensure_states() {
+    // Ensure all statics are valid (a `deref` is enough to lazily initialize them)
+    let _ = SITES.deref();
+}
+
+pub fn main() -> &'static Site {
+    &SITES[0]
+}
+
+pub fn all() -> &'static [Site] {
+    &SITES
+}
+
+pub fn find_by_key(key: &str) -> Option<&'static Site> {
+    SITES.iter().find(|site| site.key == key)
+}
+
+pub fn find_by_origin(origin: &str) -> Option<&'static Site> {
+    SITES
+        .iter()
+        .find(|site| url_origin(site.site_url).eq_ignore_ascii_case(url_origin(origin)))
+}
+
+fn url_origin(url: &str) -> &str {
+    // Notice: an origin is made of a scheme, a host and a port, so strip the \
+    //   path from the URL (if any), as well as its trailing slash.
+    let path_start = url
+        .find("://")
+        .and_then(|scheme_end| {
+            url[(scheme_end + 3)..]
+                .find('/')
+                .map(|path_start| scheme_end + 3 + path_start)
+        })
+        .unwrap_or(url.len());
+
+    &url[..path_start]
+}
@@ -9,7 +9,9 @@ use rocket_dyn_templates::handlebars::{
     RenderErrorReason,
 };
 
-use super::formatter;
+use rocket::serde::json::serde_json;
+
+use super::formatter::{self, Mention};
 
 pub fn format_line(
     helper: &Helper,
@@ -19,7 +21,7 @@ pub fn format_line(
     output: &mut dyn Output,
 ) -> HelperResult {
     if let Some(parameter) = helper.param(0) {
-        let html = formatter::linkify(parameter.value().render().as_ref());
+        let html = formatter::linkify(parameter.value().render().as_ref(), &mentions(helper));
 
         output.write(&html)?;
 
@@ -30,3 +32,31 @@ pub fn format_line(
         )))
     }
 }
+
+pub fn format_markdown(
+    helper: &Helper,
+    _: &Handlebars,
+    _: &Context,
+    _: &mut RenderContext,
+    output: &mut dyn Output,
+) -> HelperResult {
+    if let Some(parameter) = helper.param(0) {
+        let html = formatter::markdown(parameter.value().render().as_ref(), &mentions(helper));
+
+        output.write(&html)?;
+
+        Ok(())
+    } else {
+        Err(RenderError::from(RenderErrorReason::Other(
+            "Missing markdown text".to_string(),
+        )))
+    }
+}
+
+fn mentions(helper: &Helper) -> Vec<Mention> {
+    // Mentions are passed as an optional second parameter
+    helper
+        .param(1)
+        .and_then(|parameter| serde_json::from_value(parameter.value().to_owned()).ok())
+        .unwrap_or_default()
+}
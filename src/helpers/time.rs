@@ -4,36 +4,59 @@
 // Copyright: 2025, Valerian Saliou <valerian@valeriansaliou.name>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
+use chrono::format::{Item, StrftimeItems};
 use chrono::offset::Utc;
-use chrono::NaiveDateTime;
+use chrono::{DateTime, DurationRound, NaiveDateTime, TimeDelta};
+use chrono_tz::Tz;
+
+use crate::config::config::ConfigI18N;
+
+pub type Timestamp = u64;
 
 const DATETIME_FORMAT: &'static str = "%Y-%m-%d %H:%M:%S";
 
-const DATETIME_TO_DATE_FORMAT: &'static str = "%d/%m/%Y";
-const DATETIME_TO_TIME_FORMAT: &'static str = "%Hh%M";
 const DATETIME_TO_UTC_FORMAT: &'static str = "%Y-%m-%dT%H:%M:%SZ";
+const DATETIME_TO_RFC2822_FORMAT: &str = "%a, %d %b %Y %H:%M:%S +0000";
 
 const FALLBACK_DATETIME_STRING: &'static str = "(?)";
 
-pub fn now_datetime_string() -> String {
-    Utc::now().format(DATETIME_FORMAT).to_string()
+const RELATIVE_DAYS_MAXIMUM: i64 = 7;
+
+pub fn now_timestamp() -> Timestamp {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
 }
 
-pub fn now_after_datetime_string(after: Duration) -> String {
-    (Utc::now() + after).format(DATETIME_FORMAT).to_string()
+pub fn now_datetime() -> NaiveDateTime {
+    Utc::now().naive_utc()
 }
 
-pub fn parse_datetime_string(datetime: &str) -> Option<NaiveDateTime> {
-    NaiveDateTime::parse_from_str(datetime, DATETIME_FORMAT)
-        .map_err(|err| {
-            error!(
-                "could not parse datatime string: {} because: {}",
-                datetime, err
-            );
-        })
-        .ok()
+pub fn now_after_datetime(after: Duration) -> NaiveDateTime {
+    (Utc::now() + after).naive_utc()
+}
+
+pub fn next_hour_datetime() -> NaiveDateTime {
+    let now = Utc::now();
+
+    (now.duration_trunc(TimeDelta::hours(1)).unwrap_or(now) + TimeDelta::hours(1)).naive_utc()
+}
+
+pub fn timestamp_to_datetime(timestamp: Timestamp) -> NaiveDateTime {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .unwrap_or_default()
+        .naive_utc()
+}
+
+pub fn datetime_to_plain_string(datetime: &NaiveDateTime) -> String {
+    datetime.format(DATETIME_FORMAT).to_string()
+}
+
+pub fn parse_plain_datetime_string(datetime: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(datetime, DATETIME_FORMAT).ok()
 }
 
 pub fn datetime_to_string(datetime: &Option<NaiveDateTime>, format: &str) -> String {
@@ -42,14 +65,78 @@ pub fn datetime_to_string(datetime: &Option<NaiveDateTime>, format: &str) -> Str
         .unwrap_or(FALLBACK_DATETIME_STRING.to_string())
 }
 
-pub fn datetime_to_date_string(datetime: &Option<NaiveDateTime>) -> String {
-    datetime_to_string(datetime, DATETIME_TO_DATE_FORMAT)
+pub fn datetime_to_local_string(
+    datetime: &Option<NaiveDateTime>,
+    timezone: &Tz,
+    format: &str,
+) -> String {
+    datetime
+        .map(|datetime| {
+            datetime
+                .and_utc()
+                .with_timezone(timezone)
+                .format(format)
+                .to_string()
+        })
+        .unwrap_or(FALLBACK_DATETIME_STRING.to_string())
+}
+
+pub fn datetime_to_relative_string(
+    datetime: &Option<NaiveDateTime>,
+    i18n: &ConfigI18N,
+) -> Option<String> {
+    let elapsed = now_datetime() - (*datetime)?;
+
+    // Notice: past a few days, a relative datetime is not meaningful anymore, \
+    //   so that the absolute date should be used instead. Also, datetimes in \
+    //   the future (eg. due to clock skew) are considered as just now.
+    let (minutes, hours, days) = (
+        elapsed.num_minutes(),
+        elapsed.num_hours(),
+        elapsed.num_days(),
+    );
+
+    if minutes < 1 {
+        Some(i18n.relative_just_now.to_owned())
+    } else if hours < 1 {
+        Some(count_to_string(
+            minutes,
+            &i18n.relative_minute_ago,
+            &i18n.relative_minutes_ago,
+        ))
+    } else if days < 1 {
+        Some(count_to_string(
+            hours,
+            &i18n.relative_hour_ago,
+            &i18n.relative_hours_ago,
+        ))
+    } else if days <= RELATIVE_DAYS_MAXIMUM {
+        Some(count_to_string(
+            days,
+            &i18n.relative_day_ago,
+            &i18n.relative_days_ago,
+        ))
+    } else {
+        None
+    }
 }
 
-pub fn datetime_to_time_string(datetime: &Option<NaiveDateTime>) -> String {
-    datetime_to_string(datetime, DATETIME_TO_TIME_FORMAT)
+pub fn check_datetime_format(format: &str) -> bool {
+    StrftimeItems::new(format).all(|item| item != Item::Error)
 }
 
 pub fn datetime_to_utc_string(datetime: &Option<NaiveDateTime>) -> String {
     datetime_to_string(datetime, DATETIME_TO_UTC_FORMAT)
 }
+
+pub fn datetime_to_rfc2822_string(datetime: &Option<NaiveDateTime>) -> String {
+    datetime_to_string(datetime, DATETIME_TO_RFC2822_FORMAT)
+}
+
+fn count_to_string(count: i64, one: &str, other: &str) -> String {
+    if count == 1 {
+        one.to_owned()
+    } else {
+        other.replace("{count}", &count.to_string())
+    }
+}
@@ -0,0 +1,348 @@
+// Bandurria
+//
+// Lightweight comment system for static websites
+// Copyright: 2025, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::time::Duration;
+
+use hex;
+use reqwest::header::{CONTENT_TYPE, USER_AGENT};
+use reqwest::{redirect, Client};
+use rocket::serde::json::serde_json;
+use rocket::tokio;
+use uuid::Uuid;
+
+use super::authentication;
+use super::site::Site;
+use super::time::{self, Timestamp};
+use crate::config::config::ConfigWebhook;
+use crate::APP_CONF;
+
+static HTTP_USER_AGENT: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    "/",
+    env!("CARGO_PKG_VERSION"),
+    " (webhook)"
+);
+
+const RETRY_DELAY_BASE: Duration = Duration::from_secs(2);
+const RETRY_DELAY_MAXIMUM: Duration = Duration::from_secs(3600);
+
+lazy_static! {
+    static ref HTTP_CLIENT: Client = Client::builder()
+        .timeout(Duration::from_secs(10))
+        .read_timeout(Duration::from_secs(5))
+        .connect_timeout(Duration::from_secs(5))
+        .pool_idle_timeout(Duration::from_secs(20))
+        .pool_max_idle_per_host(1)
+        .redirect(redirect::Policy::none())
+        .build()
+        .unwrap();
+}
+
+#[derive(Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookEvent {
+    Submitted,
+    Approved,
+    Rejected,
+    Edited,
+    Deleted,
+    Test,
+}
+
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    event: WebhookEvent,
+    site: &'a str,
+    timestamp: Timestamp,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<WebhookPayloadPage<'a>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<WebhookPayloadComment<'a>>,
+}
+
+#[derive(Serialize)]
+struct WebhookPayloadPage<'a> {
+    path: &'a str,
+    url: String,
+}
+
+#[derive(Serialize)]
+struct WebhookPayloadComment<'a> {
+    id: &'a str,
+    url: String,
+}
+
+impl WebhookEvent {
+    pub fn all() -> Vec<Self> {
+        vec![
+            WebhookEvent::Submitted,
+            WebhookEvent::Approved,
+            WebhookEvent::Rejected,
+            WebhookEvent::Edited,
+            WebhookEvent::Deleted,
+        ]
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::Submitted => "submitted",
+            WebhookEvent::Approved => "approved",
+            WebhookEvent::Rejected => "rejected",
+            WebhookEvent::Edited => "edited",
+            WebhookEvent::Deleted => "deleted",
+            WebhookEvent::Test => "test",
+        }
+    }
+}
+
+pub fn trigger(site: &Site, event: WebhookEvent, page: &str, comment_id: &str) {
+    // Notice: deliveries run in the background, so that slow or failing \
+    //   webhooks never delay responses, nor moderation actions.
+    let webhooks = APP_CONF
+        .webhooks
+        .iter()
+        .filter(|webhook| {
+            webhook.events.contains(&event)
+                && (webhook.sites.is_empty() || webhook.sites.iter().any(|key| key == site.key))
+        })
+        .collect::<Vec<&'static ConfigWebhook>>();
+
+    if webhooks.is_empty() {
+        return;
+    }
+
+    let payload = WebhookPayload {
+        event,
+        site: site.key,
+        timestamp: time::now_timestamp(),
+        page: Some(WebhookPayloadPage {
+            path: page,
+            url: format!("{}{}", site.site_url, page),
+        }),
+        comment: Some(WebhookPayloadComment {
+            id: comment_id,
+            url: format!("{}{}#comment-{}", site.site_url, page, comment_id),
+        }),
+    };
+
+    if let Some(body) = serialize(&payload) {
+        for webhook in webhooks {
+            if let Some(signature) = sign(webhook, &body) {
+                let body = body.to_owned();
+
+                tokio::spawn(async move { deliver(webhook, event, body, signature).await });
+            }
+        }
+    }
+}
+
+pub async fn test(site: &Site) -> bool {
+    let payload = WebhookPayload {
+        event: WebhookEvent::Test,
+        site: site.key,
+        timestamp: time::now_timestamp(),
+        page: None,
+        comment: None,
+    };
+
+    let body = match serialize(&payload) {
+        Some(body) => body,
+        None => return false,
+    };
+
+    let mut delivered = true;
+
+    // Notice: test events are delivered to all webhooks, whatever the \
+    //   events they are configured for.
+    for webhook in APP_CONF.webhooks.iter() {
+        delivered &= match sign(webhook, &body) {
+            Some(signature) => {
+                deliver(webhook, WebhookEvent::Test, body.to_owned(), signature).await
+            }
+            None => false,
+        };
+    }
+
+    delivered
+}
+
+fn serialize(payload: &WebhookPayload) -> Option<String> {
+    serde_json::to_string(payload)
+        .map_err(|err| error!("could not serialize webhook payload, because: {}", err))
+        .ok()
+}
+
+fn sign(webhook: &ConfigWebhook, body: &str) -> Option<String> {
+    // Sign payload with the secret of the webhook, so that receivers can \
+    //   verify that it comes from Bandurria
+    // Important: the secret key of Bandurria must never be used there, since \
+    //   receivers would then be able to forge moderation links.
+    let signature = authentication::sign_payload_bytes_with_key(webhook.secret.as_bytes(), body)
+        .map_err(|_| error!("could not sign webhook payload for: {}", webhook.url))
+        .ok()?;
+
+    Some(format!("sha256={}", hex::encode(signature)))
+}
+
+async fn deliver(
+    webhook: &ConfigWebhook,
+    event: WebhookEvent,
+    body: String,
+    signature: String,
+) -> bool {
+    let delivery_id = Uuid::new_v4().to_string();
+
+    for attempt in 0..=webhook.retries {
+        // Wait before retrying (the delay doubles after each failed attempt, \
+        //   up to a maximum delay)
+        if attempt > 0 {
+            tokio::time::sleep(retry_delay(attempt)).await;
+        }
+
+        let response = HTTP_CLIENT
+            .post(&webhook.url)
+            .header(CONTENT_TYPE, "application/json")
+            .header(USER_AGENT, HTTP_USER_AGENT)
+            .header("X-Bandurria-Event", event.as_str())
+            .header("X-Bandurria-Delivery", &delivery_id)
+            .header("X-Bandurria-Signature", &signature)
+            .body(body.to_owned())
+            .send()
+            .await;
+
+        match response {
+            Ok(response) if response.status().is_success() => {
+                info!(
+                    "delivered webhook event: {} to: {} (attempt: {})",
+                    event.as_str(),
+                    webhook.url,
+                    attempt + 1
+                );
+
+                return true;
+            }
+            Ok(response) => {
+                warn!(
+                    "webhook event: {} was refused by: {} (code: {}, attempt: {})",
+                    event.as_str(),
+                    webhook.url,
+                    response.status().as_u16(),
+                    attempt + 1
+                );
+            }
+            Err(err) => {
+                warn!(
+                    "could not deliver webhook event: {} to: {} (attempt: {}), because: {}",
+                    event.as_str(),
+                    webhook.url,
+                    attempt + 1,
+                    err
+                );
+            }
+        }
+    }
+
+    error!(
+        "gave up delivering webhook event: {} to: {} (delivery: {})",
+        event.as_str(),
+        webhook.url,
+        delivery_id
+    );
+
+    false
+}
+
+fn retry_delay(attempt: u8) -> Duration {
+    RETRY_DELAY_BASE
+        .saturating_mul(2_u32.saturating_pow((attempt - 1).into()))
+        .min(RETRY_DELAY_MAXIMUM)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hmac::{Hmac, Mac};
+    use rocket::tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use rocket::tokio::net::TcpListener;
+    use sha2::Sha256;
+
+    #[rocket::async_test]
+    async fn it_delivers_signed_events_to_local_listener() {
+        // Notice: the crypto provider may already be installed by another test.
+        let _ = rustls::crypto::ring::default_provider().install_default();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+
+        let webhook = ConfigWebhook {
+            url: format!("http://{}/hook", listener.local_addr().unwrap()),
+            secret: "webhook-secret".to_string(),
+            events: WebhookEvent::all(),
+            sites: Vec::new(),
+            retries: 0,
+        };
+
+        let body = r#"{"event":"test","site":"default","timestamp":0}"#.to_string();
+        let signature = sign(&webhook, &body).unwrap();
+
+        // Accept a single request, and acknowledge it once fully received
+        let body_length = body.len();
+
+        let listener_task = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+
+            let (mut request, mut buffer) = (Vec::new(), [0; 4096]);
+
+            loop {
+                let read = stream.read(&mut buffer).await.unwrap();
+
+                request.extend_from_slice(&buffer[..read]);
+
+                let request_text = String::from_utf8_lossy(&request).to_string();
+
+                if let Some(headers_end) = request_text.find("\r\n\r\n") {
+                    if read == 0 || request.len() >= headers_end + 4 + body_length {
+                        break;
+                    }
+                }
+            }
+
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                .await
+                .unwrap();
+
+            String::from_utf8(request).unwrap()
+        });
+
+        assert!(deliver(&webhook, WebhookEvent::Test, body.to_owned(), signature).await);
+
+        let request = listener_task.await.unwrap();
+
+        // Verify signature header against the webhook secret
+        let mut hmac = Hmac::<Sha256>::new_from_slice(b"webhook-secret").unwrap();
+
+        hmac.update(body.as_bytes());
+
+        let expected_header = format!(
+            "x-bandurria-signature: sha256={}",
+            hex::encode(hmac.finalize().into_bytes())
+        );
+
+        assert!(request.starts_with("POST /hook "));
+        assert!(request.to_lowercase().contains(&expected_header));
+        assert!(request.to_lowercase().contains("x-bandurria-event: test"));
+        assert!(request.ends_with(&body));
+    }
+
+    #[test]
+    fn it_caps_retry_delays() {
+        assert_eq!(retry_delay(1), RETRY_DELAY_BASE);
+        assert_eq!(retry_delay(2), RETRY_DELAY_BASE * 2);
+        assert_eq!(retry_delay(u8::MAX), RETRY_DELAY_MAXIMUM);
+    }
+}
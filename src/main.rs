@@ -17,17 +17,35 @@ mod managers;
 mod routes;
 
 use std::ops::Deref;
+use std::process;
 use std::str::FromStr;
 
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, Command};
 use config::config::Config;
 use config::logger::ConfigLogger;
 use config::reader::ConfigReader;
+use helpers::{authentication, locale, rules, site, webhook};
 use log::LevelFilter;
-use managers::{email, http};
+use managers::{digest, email, http};
 
 struct AppArgs {
     config: String,
+    command: AppCommand,
+}
+
+enum AppCommand {
+    Serve,
+    GenerateSecret,
+    Render(AppRenderArgs),
+    TestWebhooks(Option<String>),
+}
+
+struct AppRenderArgs {
+    page: String,
+    site: Option<String>,
+    lang: Option<String>,
+    sort: Option<String>,
+    bare: bool,
 }
 
 lazy_static! {
@@ -47,6 +65,50 @@ fn make_app_args() -> AppArgs {
                 .help("Path to configuration file")
                 .default_value("./config.cfg"),
         )
+        .subcommand(
+            Command::new("generate-secret")
+                .about("Generates a secret key to use as security.secret_key"),
+        )
+        .subcommand(
+            Command::new("render")
+                .about("Renders the approved comments of a page as HTML")
+                .arg(
+                    Arg::new("page")
+                        .long("page")
+                        .help("Path of the page to render comments for")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("site")
+                        .long("site")
+                        .help("Key of the site the page belongs to"),
+                )
+                .arg(
+                    Arg::new("lang")
+                        .long("lang")
+                        .help("Language to render comments in"),
+                )
+                .arg(
+                    Arg::new("sort")
+                        .long("sort")
+                        .help("Order of top-level comments"),
+                )
+                .arg(
+                    Arg::new("bare")
+                        .long("bare")
+                        .help("Renders comments only, to include in a static build")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("test-webhooks")
+                .about("Delivers a test event to all webhooks")
+                .arg(
+                    Arg::new("site")
+                        .long("site")
+                        .help("Key of the site to send the test event for"),
+                ),
+        )
         .get_matches();
 
     // Generate owned app arguments
@@ -55,6 +117,79 @@ fn make_app_args() -> AppArgs {
             .get_one::<String>("config")
             .expect("invalid config value")
             .to_owned(),
+        command: match matches.subcommand() {
+            Some(("generate-secret", _)) => AppCommand::GenerateSecret,
+            Some(("render", render_matches)) => AppCommand::Render(AppRenderArgs {
+                page: render_matches
+                    .get_one::<String>("page")
+                    .expect("invalid page value")
+                    .to_owned(),
+                site: render_matches.get_one::<String>("site").cloned(),
+                lang: render_matches.get_one::<String>("lang").cloned(),
+                sort: render_matches.get_one::<String>("sort").cloned(),
+                bare: render_matches.get_flag("bare"),
+            }),
+            Some(("test-webhooks", test_matches)) => {
+                AppCommand::TestWebhooks(test_matches.get_one::<String>("site").cloned())
+            }
+            _ => AppCommand::Serve,
+        },
+    }
+}
+
+fn generate_secret() {
+    // Notice: this does not require any configuration to be loaded.
+    let secret_key = authentication::generate_secret_key().expect("could not generate secret key");
+
+    println!("{secret_key}");
+}
+
+async fn render(args: &AppRenderArgs) {
+    // Ensure all states are bound
+    // Notice: the logger is not initialized, since comments get printed to \
+    //   the standard output, which must only contain the rendered HTML.
+    ensure_states();
+
+    let snapshot = http::render_snapshot(
+        &args.page,
+        args.site.as_deref(),
+        args.lang.as_deref(),
+        args.sort.as_deref(),
+        args.bare,
+    )
+    .await;
+
+    match snapshot {
+        Ok(html) => print!("{html}"),
+        Err(status) => {
+            eprintln!("could not render comments: {status}");
+
+            process::exit(1);
+        }
+    }
+}
+
+async fn test_webhooks(site_key: Option<&str>) {
+    // Ensure all states are bound
+    ensure_states();
+
+    let site = match site_key {
+        Some(site_key) => site::find_by_key(site_key).expect("unknown site key"),
+        None => site::main(),
+    };
+
+    if APP_CONF.webhooks.is_empty() {
+        eprintln!("no webhook configured");
+
+        process::exit(1);
+    }
+
+    if webhook::test(site).await {
+        println!("delivered test event to all webhooks");
+    } else {
+        eprintln!("could not deliver test event to all webhooks (see logs)");
+
+        process::exit(1);
     }
 }
 
@@ -63,11 +198,23 @@ fn ensure_states() {
     let (_, _) = (APP_ARGS.deref(), APP_CONF.deref());
 
     // Ensure all foreign states
+    site::ensure_states();
+    locale::ensure_states();
+    authentication::ensure_states();
     email::ensure_states();
+    digest::ensure_states();
+    rules::ensure_states();
 }
 
 #[rocket::main]
 async fn main() {
+    // Run command? (that is not the server)
+    match APP_ARGS.command {
+        AppCommand::GenerateSecret => return generate_secret(),
+        AppCommand::Render(ref render_args) => return render(render_args).await,
+        _ => {}
+    }
+
     // Initialize shared logger
     let _logger = ConfigLogger::init(
         LevelFilter::from_str(&APP_CONF.server.log_level).expect("invalid log level"),
@@ -78,6 +225,11 @@ async fn main() {
         .install_default()
         .expect("failed to install rustls crypto provider");
 
+    // Run command? (that needs the logger and HTTP client)
+    if let AppCommand::TestWebhooks(ref site_key) = APP_ARGS.command {
+        return test_webhooks(site_key.as_deref()).await;
+    }
+
     info!("starting up");
 
     // Ensure all states are bound
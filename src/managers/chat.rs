@@ -0,0 +1,265 @@
+// Bandurria
+//
+// Lightweight comment system for static websites
+// Copyright: 2025, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::time::Duration;
+
+use reqwest::header::{CONTENT_TYPE, USER_AGENT};
+use reqwest::{redirect, Client};
+use rocket::serde::json::serde_json::{self, json, Value};
+
+use crate::config::config::ConfigChat;
+
+const ZERO_WIDTH_SPACE: char = '\u{200B}';
+
+const SLACK_FORMATTING_CHARACTERS: &[char] = &['*', '_', '~', '`'];
+
+const MATTERMOST_FORMATTING_CHARACTERS: &[char] = &[
+    '\\', '`', '*', '_', '{', '}', '[', ']', '(', ')', '<', '>', '#', '+', '-', '.', '!', '|', '~',
+    ':',
+];
+
+static HTTP_USER_AGENT: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    "/",
+    env!("CARGO_PKG_VERSION"),
+    " (chat)"
+);
+
+lazy_static! {
+    static ref HTTP_CLIENT: Client = Client::builder()
+        .timeout(Duration::from_secs(10))
+        .read_timeout(Duration::from_secs(5))
+        .connect_timeout(Duration::from_secs(5))
+        .pool_idle_timeout(Duration::from_secs(20))
+        .pool_max_idle_per_host(1)
+        .redirect(redirect::Policy::none())
+        .build()
+        .unwrap();
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatKind {
+    Slack,
+    Mattermost,
+    Matrix,
+}
+
+pub struct ChatMessage<'a> {
+    pub title: String,
+    pub intro: String,
+    pub text: &'a str,
+    pub links: Vec<(&'static str, String)>,
+}
+
+impl ChatMessage<'_> {
+    fn to_slack(&self) -> Value {
+        // Notice: the comment text is wrapped in a code block, so that any \
+        //   formatting or mention it contains gets displayed as-is.
+        let mut text = format!(
+            "*{}*\n{}\n",
+            escape_slack(&self.title),
+            escape_slack(&self.intro)
+        );
+
+        text.push_str(&format!(
+            "```\n{}\n```\n",
+            escape_code_block(&escape_markup(self.text))
+        ));
+
+        text.push('\n');
+        text.push_str(&self.join_links(|label, url| format!("<{url}|{label}>")));
+
+        json!({ "text": text })
+    }
+
+    fn to_mattermost(&self) -> Value {
+        let mut text = format!(
+            "**{}**\n{}\n",
+            escape_mattermost(&self.title),
+            escape_mattermost(&self.intro)
+        );
+
+        text.push_str(&format!("```\n{}\n```\n", escape_code_block(self.text)));
+
+        text.push('\n');
+        text.push_str(&self.join_links(|label, url| format!("[{label}]({url})")));
+
+        json!({ "text": text })
+    }
+
+    fn to_matrix(&self) -> Value {
+        // Notice: Matrix incoming webhooks (eg. hookshot) accept both a plain \
+        //   text and an HTML version of the message.
+        let text = format!(
+            "{}\n{}\n\n{}\n\n{}",
+            neutralize_mentions(&self.title),
+            neutralize_mentions(&self.intro),
+            neutralize_mentions(self.text),
+            self.join_links(|label, url| format!("{label}: {url}"))
+        );
+
+        let html = format!(
+            "<p><strong>{}</strong><br />{}</p><blockquote>{}</blockquote><p>{}</p>",
+            escape_markup(&neutralize_mentions(&self.title)),
+            escape_markup(&neutralize_mentions(&self.intro)),
+            neutralize_mentions(self.text)
+                .lines()
+                .map(escape_markup)
+                .collect::<Vec<String>>()
+                .join("<br />"),
+            self.join_links(|label, url| format!(
+                "<a href=\"{}\">{}</a>",
+                escape_markup(url),
+                label
+            ))
+        );
+
+        json!({ "text": text, "html": html })
+    }
+
+    fn join_links<F>(&self, format_link: F) -> String
+    where
+        F: Fn(&str, &str) -> String,
+    {
+        self.links
+            .iter()
+            .map(|(label, url)| format_link(label, url))
+            .collect::<Vec<String>>()
+            .join(" · ")
+    }
+}
+
+pub async fn deliver_faillible(chat: &ConfigChat, message: &ChatMessage<'_>) -> Result<(), ()> {
+    let payload = match chat.kind {
+        ChatKind::Slack => message.to_slack(),
+        ChatKind::Mattermost => message.to_mattermost(),
+        ChatKind::Matrix => message.to_matrix(),
+    };
+
+    let response = HTTP_CLIENT
+        .post(&chat.url)
+        .header(CONTENT_TYPE, "application/json")
+        .header(USER_AGENT, HTTP_USER_AGENT)
+        .body(serde_json::to_string(&payload).or(Err(()))?)
+        .send()
+        .await
+        .or(Err(()))?;
+
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(())
+    }
+}
+
+pub async fn deliver(chat: &ConfigChat, message: &ChatMessage<'_>) {
+    deliver_faillible(chat, message)
+        .await
+        .map_err(|_| {
+            error!(
+                "failed delivering chat message to: {}, with title: '{}'",
+                chat.url, message.title
+            )
+        })
+        .map(|_| info!("delivered chat message to: {}", chat.url))
+        .ok();
+}
+
+fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn escape_slack(text: &str) -> String {
+    // Notice: Slack has no escape sequence for its formatting characters, \
+    //   therefore they get surrounded with zero-width spaces, which prevents \
+    //   them from being matched as formatting.
+    let mut escaped = String::with_capacity(text.len());
+
+    for character in neutralize_mentions(&escape_markup(text)).chars() {
+        if SLACK_FORMATTING_CHARACTERS.contains(&character) {
+            escaped.push(ZERO_WIDTH_SPACE);
+            escaped.push(character);
+            escaped.push(ZERO_WIDTH_SPACE);
+        } else {
+            escaped.push(character);
+        }
+    }
+
+    escaped
+}
+
+fn escape_mattermost(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for character in neutralize_mentions(text).chars() {
+        if MATTERMOST_FORMATTING_CHARACTERS.contains(&character) {
+            escaped.push('\\');
+        }
+
+        escaped.push(character);
+    }
+
+    escaped
+}
+
+fn escape_code_block(text: &str) -> String {
+    // Notice: break any sequence of backticks, so that the text cannot close \
+    //   the code block it is wrapped in.
+    text.replace('`', &format!("`{ZERO_WIDTH_SPACE}"))
+}
+
+fn neutralize_mentions(text: &str) -> String {
+    // Notice: a zero-width space after '@' prevents mentions such as \
+    //   '@channel', '@all' or '@here' from notifying anyone.
+    text.replace('@', &format!("@{ZERO_WIDTH_SPACE}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_message(text: &str) -> ChatMessage<'_> {
+        ChatMessage {
+            title: "New comment".to_string(),
+            intro: "*John* [@channel](https://example.com) said:".to_string(),
+            text,
+            links: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn it_escapes_slack_messages() {
+        let payload = make_message("<!channel> ```@here").to_slack();
+        let text = payload["text"].as_str().unwrap();
+
+        assert!(!text.contains("*John*"));
+        assert!(!text.contains("@channel"));
+        assert!(!text.contains("<!channel>"));
+        assert_eq!(text.matches("```").count(), 2);
+    }
+
+    #[test]
+    fn it_escapes_mattermost_messages() {
+        let payload = make_message("@all ```\n**bold**").to_mattermost();
+        let text = payload["text"].as_str().unwrap();
+
+        assert!(text.contains("\\*John\\*"));
+        assert!(text.contains("\\[@\u{200B}channel\\]"));
+        assert!(text.contains("```\n@all `\u{200B}`\u{200B}`\u{200B}\n**bold**\n```"));
+        assert_eq!(text.matches("```").count(), 2);
+    }
+
+    #[test]
+    fn it_neutralizes_matrix_mentions() {
+        let payload = make_message("@room").to_matrix();
+
+        assert!(!payload["text"].as_str().unwrap().contains("@room"));
+        assert!(!payload["html"].as_str().unwrap().contains("@room"));
+    }
+}
@@ -0,0 +1,78 @@
+// Bandurria
+//
+// Lightweight comment system for static websites
+// Copyright: 2025, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use std::ops::Deref;
+
+use chrono::Timelike;
+use rocket::tokio;
+use rocket_db_pools::sqlx::MySqlPool;
+
+use crate::helpers::notifier::{self, AlertFrequency};
+use crate::helpers::{site, time};
+use crate::APP_CONF;
+
+lazy_static! {
+    static ref DIGEST_HOUR: u32 = make_digest_hour();
+}
+
+fn make_digest_hour() -> u32 {
+    let digest_hour = APP_CONF.email.admin_digest_hour as u32;
+
+    if digest_hour > 23 {
+        panic!("email.admin_digest_hour must be an hour of the day (from 0 to 23)");
+    }
+
+    digest_hour
+}
+
+pub fn ensure_states() {
+    // Ensure all statics are valid (a `deref` is enough to lazily initialize them)
+    let _ = DIGEST_HOUR.deref();
+}
+
+pub fn schedule(pool: MySqlPool) {
+    // Do not run the scheduler if no admin wants to be alerted with digests
+    if !notifier::has_digest_admins() {
+        debug!("not scheduling digests (no admin wants them)");
+
+        return;
+    }
+
+    info!("scheduling digests");
+
+    tokio::spawn(async move {
+        loop {
+            // Digests are sent at the start of each hour
+            let until = time::next_hour_datetime();
+
+            tokio::time::sleep((until - time::now_datetime()).to_std().unwrap_or_default()).await;
+
+            debug!("running digests until: {}", until);
+
+            for site in site::all() {
+                notifier::alert_of_pending_comments_digest_to_admins(
+                    &pool,
+                    site,
+                    AlertFrequency::Hourly,
+                    &until,
+                )
+                .await;
+
+                // Daily digests are sent at the configured hour, in the \
+                //   timezone of the site
+                if until.and_utc().with_timezone(&site.timezone).hour() == *DIGEST_HOUR {
+                    notifier::alert_of_pending_comments_digest_to_admins(
+                        &pool,
+                        site,
+                        AlertFrequency::Daily,
+                        &until,
+                    )
+                    .await;
+                }
+            }
+        }
+    });
+}
@@ -8,15 +8,19 @@ use std::path::PathBuf;
 
 use rocket::config::Ident;
 use rocket::data::{Limits, ToByteUnit};
+use rocket::fairing::AdHoc;
 use rocket::figment::Figment;
 use rocket::fs::FileServer;
-use rocket::{self, Config};
+use rocket::http::{RawStr, Status};
+use rocket::local::asynchronous::Client;
+use rocket::{self, Build, Config, Rocket};
 use rocket_db_pools::sqlx;
 use rocket_db_pools::{Connection, Database};
 use rocket_dyn_templates::Template;
 
+use super::digest;
 use crate::helpers::template;
-use crate::routes::{api, image, page};
+use crate::routes::{api, feed, image, page};
 use crate::APP_CONF;
 
 #[derive(Database)]
@@ -54,25 +58,103 @@ fn assets_path(kind: &str) -> PathBuf {
         .to_path_buf()
 }
 
-pub async fn bootstrap() -> rocket::Rocket<rocket::Build> {
-    rocket::custom(configure())
+fn build(figment: Figment) -> Rocket<Build> {
+    rocket::custom(figment)
         .attach(Db::init())
         .attach(Template::custom(|engines| {
             engines
                 .handlebars
                 .register_helper("format-line", Box::new(template::format_line));
+            engines
+                .handlebars
+                .register_helper("format-markdown", Box::new(template::format_markdown));
         }))
         .mount(
             "/api",
             rocket::routes![
                 api::get_base,
+                api::get_comments,
+                api::get_counts,
                 api::post_comment,
                 api::post_challenge,
-                api::get_admin_moderate_comment
+                api::post_reaction_challenge,
+                api::post_reaction,
+                api::delete_reaction,
+                api::get_admin_moderate_comment,
+                api::post_admin_moderate_comment
             ],
         )
-        .mount("/page", rocket::routes![page::get_comments])
+        .mount(
+            "/page",
+            rocket::routes![
+                page::get_comments,
+                page::get_snapshot,
+                page::get_form,
+                page::post_comment
+            ],
+        )
+        .mount("/feed", rocket::routes![feed::get_atom, feed::get_rss])
         .mount("/image", rocket::routes![image::get_avatar])
         .mount("/assets", FileServer::from(assets_path("public")))
         .mount("/dev", FileServer::from(assets_path("dev")))
 }
+
+pub async fn bootstrap() -> Rocket<Build> {
+    // Notice: digests are only scheduled by the server, and not when \
+    //   rendering snapshots (which builds its own local instance).
+    build(configure()).attach(AdHoc::on_liftoff("Digest Scheduler", |rocket| {
+        Box::pin(async move {
+            if let Some(db) = Db::fetch(rocket) {
+                digest::schedule(db.0.clone());
+            }
+        })
+    }))
+}
+
+pub async fn render_snapshot(
+    page: &str,
+    site: Option<&str>,
+    lang: Option<&str>,
+    sort: Option<&str>,
+    bare: bool,
+) -> Result<String, Status> {
+    // Notice: the snapshot is rendered from a local request to its route, \
+    //   so that it is the same as when requested over HTTP (which it is \
+    //   always allowed to, as it is marked local). Rocket logs are \
+    //   silenced, since the snapshot gets printed to the standard output.
+    // Important: Rocket errors must be handled (ie. printed), as they would \
+    //   abort the process upon being dropped otherwise.
+    let rocket = build(configure().merge(("log_level", "off"))).manage(page::LocalSnapshots);
+
+    let client = Client::untracked(rocket).await.map_err(|err| {
+        err.pretty_print();
+
+        Status::ServiceUnavailable
+    })?;
+
+    let mut query = format!("page={}", RawStr::new(page).percent_encode());
+
+    for (key, value) in [("site", site), ("lang", lang), ("sort", sort)] {
+        if let Some(value) = value {
+            query.push_str(&format!("&{}={}", key, RawStr::new(value).percent_encode()));
+        }
+    }
+
+    if bare {
+        query.push_str("&bare=true");
+    }
+
+    let response = client
+        .get(format!("/page/snapshot?{}", query))
+        .dispatch()
+        .await;
+
+    if response.status() != Status::Ok {
+        return Err(response.status());
+    }
+
+    response
+        .into_string()
+        .await
+        .ok_or(Status::InternalServerError)
+}
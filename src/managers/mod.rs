@@ -4,5 +4,7 @@
 // Copyright: 2025, Valerian Saliou <valerian@valeriansaliou.name>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
+pub mod chat;
+pub mod digest;
 pub mod email;
 pub mod http;
@@ -4,22 +4,35 @@
 // Copyright: 2025, Valerian Saliou <valerian@valeriansaliou.name>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use rocket::form::{Form, FromForm};
 use rocket::http::Status;
+use rocket::request::Request;
+use rocket::response::{Responder, Result as ResponseResult};
 use rocket::serde::json::Json;
-use rocket::{get, post};
+use rocket::{delete, get, post};
+use rocket_dyn_templates::{context, Template};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use validator::Validate;
 
-use crate::helpers::{authentication, mint, notifier, query};
+use crate::helpers::locale::Locale;
+use crate::helpers::site::Site;
+use crate::helpers::webhook::{self, WebhookEvent};
+use crate::helpers::{authentication, formatter, mint, normalize, notifier, query, rules, time};
 use crate::managers::http::DbConn;
+use crate::APP_CONF;
+
+const COUNTS_PAGES_MAXIMUM: usize = 100;
 
 #[derive(Deserialize, Validate)]
 pub struct CommentData {
     #[validate(length(equal = 36))]
     comment_id: String,
 
-    #[validate(length(equal = 64))]
+    #[validate(length(max = 128))]
     attestation: String,
 
     #[validate(length(min = 1, max = 80))]
@@ -34,6 +47,54 @@ pub struct CommentData {
     mints: Vec<String>,
     reply_to: Option<String>,
     alerts_subscribe: bool,
+
+    #[serde(default)]
+    website: String,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ChallengeData {
+    #[validate(length(max = 128))]
+    stamp: String,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ReactionData {
+    #[validate(length(equal = 36))]
+    reaction_id: String,
+
+    #[validate(length(equal = 36))]
+    comment_id: String,
+
+    #[validate(length(min = 1, max = 32))]
+    reaction: String,
+
+    mints: Vec<String>,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct ReactionRemoveData {
+    #[validate(length(equal = 36))]
+    comment_id: String,
+
+    #[validate(length(min = 1, max = 32))]
+    reaction: String,
+}
+
+pub struct CommentSubmission<'a> {
+    pub comment_id: &'a str,
+    pub name: &'a str,
+    pub email: &'a str,
+    pub text: &'a str,
+    pub reply_to: &'a Option<String>,
+    pub alerts_subscribe: bool,
+}
+
+#[derive(FromForm)]
+pub struct ModerationData {
+    signature: String,
+    action: String,
+    expire: time::Timestamp,
 }
 
 #[derive(Serialize)]
@@ -42,6 +103,25 @@ pub struct BaseResponse<D> {
     pub data: D,
 }
 
+pub struct ErrorResponse {
+    status: Status,
+    reason: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+pub struct CommentsResponseData {
+    comments: Vec<query::CommentNode>,
+    cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct CountResponseData {
+    count: i64,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_comment_at: Option<String>,
+}
+
 #[derive(Serialize)]
 pub struct ChallengeResponseData {
     comment_id: String,
@@ -51,6 +131,62 @@ pub struct ChallengeResponseData {
     solutions_expect: mint::MintSolutions,
 }
 
+#[derive(Serialize)]
+pub struct ReactionChallengeResponseData {
+    reaction_id: String,
+    problems: Vec<String>,
+    difficulty_expect: mint::MintDifficulty,
+    solutions_expect: mint::MintSolutions,
+}
+
+#[derive(Serialize)]
+pub struct ReactionResponseData {
+    count: i64,
+}
+
+impl ErrorResponse {
+    pub fn new(status: Status, reason: &'static str) -> Self {
+        ErrorResponse {
+            status,
+            reason: Some(reason),
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    pub fn reason(&self) -> Option<&'static str> {
+        self.reason
+    }
+}
+
+impl From<Status> for ErrorResponse {
+    fn from(status: Status) -> Self {
+        ErrorResponse {
+            status,
+            reason: None,
+        }
+    }
+}
+
+impl<'r, 'o: 'r> Responder<'r, 'o> for ErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> ResponseResult<'o> {
+        // Errors with a reason carry it in their body, so that the embed can \
+        //   show a meaningful message, while other errors get forwarded to \
+        //   the default catcher.
+        if let Some(reason) = self.reason {
+            let mut response = Json(BaseResponse { reason, data: () }).respond_to(request)?;
+
+            response.set_status(self.status);
+
+            Ok(response)
+        } else {
+            Err(self.status)
+        }
+    }
+}
+
 #[get("/")]
 pub async fn get_base() -> Result<Json<BaseResponse<()>>, Status> {
     Ok(Json(BaseResponse {
@@ -59,15 +195,113 @@ pub async fn get_base() -> Result<Json<BaseResponse<()>>, Status> {
     }))
 }
 
+#[get("/comments?<page>&<sort>&<cursor>")]
+pub async fn get_comments(
+    mut db: DbConn,
+    site: &Site,
+    locale: Locale,
+    page: &str,
+    sort: Option<&str>,
+    cursor: Option<&str>,
+) -> Result<Json<BaseResponse<CommentsResponseData>>, Status> {
+    // Fetch a page of comments (as a tree of threads)
+    let (comments, next_cursor) =
+        query::list_comments_for_page(&mut db, site, &locale, page, sort, cursor).await?;
+
+    Ok(Json(BaseResponse {
+        reason: "listed",
+        data: CommentsResponseData {
+            comments,
+            cursor: next_cursor.map(|next_cursor| next_cursor.encode()),
+        },
+    }))
+}
+
+#[get("/counts?<page>&<last>")]
+pub async fn get_counts(
+    mut db: DbConn,
+    site: &Site,
+    page: Vec<&str>,
+    last: Option<bool>,
+) -> Result<Json<BaseResponse<HashMap<String, CountResponseData>>>, Status> {
+    // Too many pages requested at once?
+    if page.len() > COUNTS_PAGES_MAXIMUM {
+        return Err(Status::PayloadTooLarge);
+    }
+
+    // Normalize all page URLs (pages that map to the same URL get counted once)
+    let pages = page
+        .into_iter()
+        .map(|page| Ok((page, normalize::page_url(page)?)))
+        .collect::<Result<Vec<(&str, String)>, Status>>()?;
+
+    let mut page_urls: Vec<String> = pages
+        .iter()
+        .map(|(_, page_url)| page_url.to_owned())
+        .collect();
+
+    page_urls.sort();
+    page_urls.dedup();
+
+    // Count approved comments for all pages
+    let counts = query::count_comments_for_page_urls(&mut db, site, &page_urls).await?;
+
+    // Map counts back to requested pages (pages with no comment count zero)
+    let data = pages
+        .into_iter()
+        .map(|(page, page_url)| {
+            let (count, last_created_at) = counts
+                .get(&page_url)
+                .map(|(count, last_created_at)| (*count, *last_created_at))
+                .unwrap_or((0, None));
+
+            (
+                page.to_string(),
+                CountResponseData {
+                    count,
+                    last_comment_at: if last == Some(true) {
+                        last_created_at.map(|last_created_at| {
+                            time::datetime_to_utc_string(&Some(last_created_at))
+                        })
+                    } else {
+                        None
+                    },
+                },
+            )
+        })
+        .collect();
+
+    Ok(Json(BaseResponse {
+        reason: "counted",
+        data,
+    }))
+}
+
 #[post("/comment?<page>", format = "json", data = "<comment>")]
 pub async fn post_comment(
     mut db: DbConn,
+    site: &Site,
     page: &str,
     comment: Json<CommentData>,
-) -> Result<Json<BaseResponse<()>>, Status> {
+) -> Result<Json<BaseResponse<()>>, ErrorResponse> {
     // Data is invalid?
     if comment.validate().is_err() {
-        return Err(Status::UnprocessableEntity);
+        return Err(Status::UnprocessableEntity.into());
+    }
+
+    // Honeypot field is filled? (this is a bot)
+    // Notice: pretend that the comment got submitted, so that the bot does \
+    //   not learn that it got caught.
+    if !comment.website.is_empty() {
+        warn!(
+            "caught bot in honeypot for comment: {}, discarding it",
+            comment.comment_id
+        );
+
+        return Ok(Json(BaseResponse {
+            reason: "submitted",
+            data: (),
+        }));
     }
 
     // Read raw input data
@@ -75,55 +309,66 @@ pub async fn post_comment(
     let attestation = comment.attestation.as_str();
 
     // Clean input data
-    let email = comment.email.trim();
-    let name = comment.name.trim();
-    let text = comment.text.trim();
+    let submission = CommentSubmission {
+        comment_id,
+        name: comment.name.trim(),
+        email: comment.email.trim(),
+        text: comment.text.trim(),
+        reply_to: &comment.reply_to,
+        alerts_subscribe: comment.alerts_subscribe,
+    };
 
-    // Data is empty?
-    if email.is_empty() || name.is_empty() || text.is_empty() {
-        return Err(Status::BadRequest);
-    }
+    // Check comment data (before any expensive verification work)
+    check_comment(&mut db, site, page, &submission).await?;
 
     // Verify attestation
-    if !authentication::verify_challenge_attestation(page, comment_id, attestation) {
-        return Err(Status::Unauthorized);
+    // Important: this must be done before any mint verification work is \
+    //   spent, since verifying mints is expensive.
+    let (issued_at, expire_at) =
+        authentication::verify_challenge_attestation(site, page, comment_id, attestation)
+            .ok_or(Status::Unauthorized)?;
+
+    // Comment was submitted too fast? (humans cannot be that fast)
+    if time::now_timestamp() < issued_at + site.antispam.submit_delay_minimum {
+        warn!(
+            "rejected comment: {} because it was submitted too fast",
+            comment_id
+        );
+
+        return Err(ErrorResponse::new(
+            Status::TooManyRequests,
+            "submit_too_fast",
+        ));
+    }
+
+    // Attestation was already consumed? (it can only be used once)
+    // Notice: this is checked before verifying mints, so that replayed \
+    //   attestations do not cost any mint verification work.
+    if query::check_attestation_consumed(&mut db, comment_id).await? {
+        warn!("rejected replayed attestation for comment: {}", comment_id);
+
+        return Err(Status::Conflict.into());
     }
 
     // Verify mints
     let is_mint_verified =
-        mint::verify(comment_id, &comment.mints).or(Err(Status::InternalServerError))?;
+        mint::verify(comment_id, &comment.mints, mint::MintPurpose::Comment(site))
+            .or(Err(Status::InternalServerError))?;
 
     if !is_mint_verified {
-        return Err(Status::PaymentRequired);
-    }
-
-    // Comment already exists? (this should not happen!)
-    if query::check_comment_exists(&mut db, comment_id).await? {
-        return Err(Status::Conflict);
+        return Err(Status::PaymentRequired.into());
     }
 
-    // Acquire page and author identifiers
-    let page_id = query::find_or_create_page_id(&mut db, page).await?;
-    let author_id = query::find_or_create_author_id(&mut db, &email, &name).await?;
+    // Consume attestation (only once mints are verified, so that a \
+    //   submission with wrong mints does not burn a valid attestation)
+    if !query::consume_attestation(&mut db, comment_id, expire_at).await? {
+        warn!("rejected replayed attestation for comment: {}", comment_id);
 
-    // Subscribe author to alerts? (store email value)
-    if comment.alerts_subscribe {
-        query::update_author_email(&mut db, &author_id, Some(&email)).await?
+        return Err(Status::Conflict.into());
     }
 
-    // Insert comment for page and author
-    query::insert_comment_for_page_id_and_author_id(
-        &mut db,
-        comment_id,
-        &text,
-        &page_id,
-        &author_id,
-        &comment.reply_to,
-    )
-    .await?;
-
-    // Notify admins of new comment
-    notifier::alert_of_new_comment_to_admins(comment_id, page, name, email, text).await;
+    // Insert comment (and notify of it)
+    insert_comment(&mut db, site, page, &submission).await?;
 
     Ok(Json(BaseResponse {
         reason: "submitted",
@@ -131,17 +376,47 @@ pub async fn post_comment(
     }))
 }
 
-#[post("/challenge?<page>", format = "json")]
+#[post("/challenge?<page>", format = "json", data = "<challenge>")]
 pub async fn post_challenge(
+    mut db: DbConn,
+    site: &Site,
     page: &str,
-) -> Result<Json<BaseResponse<ChallengeResponseData>>, Status> {
+    challenge: Json<ChallengeData>,
+) -> Result<Json<BaseResponse<ChallengeResponseData>>, ErrorResponse> {
+    // Data is invalid?
+    if challenge.validate().is_err() {
+        return Err(Status::UnprocessableEntity.into());
+    }
+
+    // Verify form stamp (it tells when the form was rendered)
+    let (rendered_at, _) = authentication::verify_form_stamp(site, page, &challenge.stamp)
+        .ok_or(Status::Unauthorized)?;
+
+    // Comments are closed on page?
+    if query::check_page_closed(&mut db, site, page).await? {
+        return Err(ErrorResponse::new(Status::Forbidden, "comments_closed"));
+    }
+
     // Generate a future comment ID and sign it to attest of its origin
     let comment_id = Uuid::new_v4().to_string();
-    let attestation = authentication::generate_challenge_attestation(page, &comment_id)?;
+
+    // Notice: the attestation is issued at the time the form was rendered, \
+    //   so that the minimum submit delay measures how long the reader spent \
+    //   writing their comment (and not how long it took to solve the mints).
+    let expire_at = time::now_timestamp() + site.antispam.attestation_expire_seconds;
+
+    let attestation = authentication::generate_challenge_attestation(
+        site,
+        page,
+        &comment_id,
+        rendered_at,
+        expire_at,
+    )?;
 
     // Generate challenge
     let (problems, difficulty_expect, solutions_expect) =
-        mint::challenge(&comment_id).or(Err(Status::InternalServerError))?;
+        mint::challenge(&comment_id, mint::MintPurpose::Comment(site))
+            .or(Err(Status::InternalServerError))?;
 
     Ok(Json(BaseResponse {
         reason: "generated",
@@ -155,49 +430,428 @@ pub async fn post_challenge(
     }))
 }
 
-#[get("/admin/moderate/<comment_id>?<signature>&<action>")]
+#[post("/reaction/challenge", format = "json")]
+pub async fn post_reaction_challenge(
+) -> Result<Json<BaseResponse<ReactionChallengeResponseData>>, Status> {
+    // Ensure reactions are enabled
+    if APP_CONF.reactions.kinds.is_empty() {
+        return Err(Status::Gone);
+    }
+
+    // Generate a reaction ID, that the lightweight challenge is bound to
+    let reaction_id = Uuid::new_v4().to_string();
+
+    let (problems, difficulty_expect, solutions_expect) =
+        mint::challenge(&reaction_id, mint::MintPurpose::Reaction)
+            .or(Err(Status::InternalServerError))?;
+
+    Ok(Json(BaseResponse {
+        reason: "generated",
+        data: ReactionChallengeResponseData {
+            reaction_id,
+            problems,
+            difficulty_expect,
+            solutions_expect,
+        },
+    }))
+}
+
+#[post("/reaction?<page>", format = "json", data = "<reaction>")]
+pub async fn post_reaction(
+    mut db: DbConn,
+    site: &Site,
+    client_ip: IpAddr,
+    page: &str,
+    reaction: Json<ReactionData>,
+) -> Result<Json<BaseResponse<ReactionResponseData>>, Status> {
+    // Validate input data
+    reaction.validate().or(Err(Status::UnprocessableEntity))?;
+
+    // Ensure reaction is allowed
+    if !APP_CONF.reactions.kinds.contains(&reaction.reaction) {
+        return Err(Status::UnprocessableEntity);
+    }
+
+    // Verify mint (the challenge solutions are bound to the reaction ID)
+    let is_mint_valid = mint::verify(
+        &reaction.reaction_id,
+        &reaction.mints,
+        mint::MintPurpose::Reaction,
+    )
+    .or(Err(Status::InternalServerError))?;
+
+    if !is_mint_valid {
+        return Err(Status::Forbidden);
+    }
+
+    // Ensure comment can be reacted to (before consuming the reaction ID, so \
+    //   that solutions do not get burned on a comment that cannot be reacted to)
+    check_reaction_comment(&mut db, site, page, &reaction.comment_id).await?;
+
+    // Consume reaction ID, so that its solutions cannot be replayed
+    // Notice: the consumed reaction ID must be kept for as long as its \
+    //   solutions are valid, hence the mint validity.
+    let expire_at = time::now_timestamp() + mint::VALIDITY.as_secs();
+
+    if !query::consume_attestation(&mut db, &reaction.reaction_id, expire_at).await? {
+        return Err(Status::Conflict);
+    }
+
+    // Insert reaction (once per client)
+    let client_hash =
+        authentication::generate_reaction_client_hash(&client_ip, &reaction.comment_id)?;
+
+    query::insert_reaction(
+        &mut db,
+        &reaction.comment_id,
+        &reaction.reaction,
+        &client_hash,
+    )
+    .await?;
+
+    Ok(Json(BaseResponse {
+        reason: "reacted",
+        data: ReactionResponseData {
+            count: query::count_reactions_for_comment_id(
+                &mut db,
+                &reaction.comment_id,
+                &reaction.reaction,
+            )
+            .await?,
+        },
+    }))
+}
+
+#[delete("/reaction?<page>", format = "json", data = "<reaction>")]
+pub async fn delete_reaction(
+    mut db: DbConn,
+    site: &Site,
+    client_ip: IpAddr,
+    page: &str,
+    reaction: Json<ReactionRemoveData>,
+) -> Result<Json<BaseResponse<ReactionResponseData>>, Status> {
+    // Validate input data
+    reaction.validate().or(Err(Status::UnprocessableEntity))?;
+
+    // Ensure comment can be reacted to (reactions are frozen once closed)
+    check_reaction_comment(&mut db, site, page, &reaction.comment_id).await?;
+
+    // Remove reaction (only the client that added it can remove it)
+    let client_hash =
+        authentication::generate_reaction_client_hash(&client_ip, &reaction.comment_id)?;
+
+    query::remove_reaction(
+        &mut db,
+        &reaction.comment_id,
+        &reaction.reaction,
+        &client_hash,
+    )
+    .await?;
+
+    Ok(Json(BaseResponse {
+        reason: "unreacted",
+        data: ReactionResponseData {
+            count: query::count_reactions_for_comment_id(
+                &mut db,
+                &reaction.comment_id,
+                &reaction.reaction,
+            )
+            .await?,
+        },
+    }))
+}
+
+#[get("/admin/moderate/<comment_id>?<signature>&<action>&<expire>")]
 pub async fn get_admin_moderate_comment(
     mut db: DbConn,
     comment_id: &str,
     signature: &str,
     action: &str,
-) -> Result<&'static str, Status> {
+    expire: time::Timestamp,
+) -> Result<Template, Status> {
+    // Important: verify signature first things first
+    if !authentication::verify_admin_comment_signature(action, comment_id, expire, signature) {
+        return Err(Status::Unauthorized);
+    }
+
+    let action_label = moderation_action_label(action).ok_or(Status::BadRequest)?;
+
+    // Resolve comment author, page and text
+    // Notice: this does not perform any change, since links might be \
+    //   opened by link prefetchers or email scanners. Moderation actions \
+    //   are only performed upon confirming them (over POST).
+    let comment_author = query::resolve_comment_author_email_name(&mut db, comment_id).await?;
+    let comment_page_and_text = query::resolve_comment_page_and_text(&mut db, comment_id).await?;
+
+    if let (Some(comment_author), Some((comment_site, comment_page, comment_text))) =
+        (comment_author, comment_page_and_text)
+    {
+        Ok(Template::render(
+            "moderate",
+            context! {
+                comment: context! {
+                    name: comment_author.2,
+                    url: format!("{}{}#comment-{}", comment_site.site_url, comment_page, comment_id),
+                    lines: comment_text.lines().filter(|line| !line.is_empty()).collect::<Vec<&str>>(),
+                },
+                moderation: context! {
+                    label: action_label,
+                    action,
+                    expire,
+                    signature,
+                },
+            },
+        ))
+    } else {
+        // Comment does not exist anymore? (treat as non-error)
+        Ok(Template::render(
+            "moderate",
+            context! { message: "Comment does not exist anymore." },
+        ))
+    }
+}
+
+#[post("/admin/moderate/<comment_id>", data = "<moderation>")]
+pub async fn post_admin_moderate_comment(
+    mut db: DbConn,
+    comment_id: &str,
+    moderation: Form<ModerationData>,
+) -> Result<Template, Status> {
     // Important: verify signature first things first
-    if !authentication::verify_admin_comment_signature("moderate", comment_id, signature) {
+    if !authentication::verify_admin_comment_signature(
+        &moderation.action,
+        comment_id,
+        moderation.expire,
+        &moderation.signature,
+    ) {
         return Err(Status::Unauthorized);
     }
 
+    let message = moderate_comment(&mut db, comment_id, &moderation.action).await?;
+
+    // Provide a link to undo flag changes (eg. unpin a pinned comment)
+    let undo = moderation_undo_action(&moderation.action).map(|undo_action| {
+        context! {
+            label: moderation_action_label(undo_action),
+            url: notifier::make_moderation_url(comment_id, undo_action),
+        }
+    });
+
+    Ok(Template::render("moderate", context! { message, undo }))
+}
+
+pub async fn check_comment(
+    db: &mut DbConn,
+    site: &Site,
+    page: &str,
+    submission: &CommentSubmission<'_>,
+) -> Result<(), ErrorResponse> {
+    // Data is empty?
+    if submission.email.is_empty() || submission.name.is_empty() || submission.text.is_empty() {
+        return Err(Status::BadRequest.into());
+    }
+
+    // Comments are closed on page?
+    if query::check_page_closed(db, site, page).await? {
+        return Err(ErrorResponse::new(Status::Forbidden, "comments_closed"));
+    }
+
+    // Text breaks content rules?
+    if let Err(violation) = rules::check_text(site, submission.text) {
+        info!(
+            "rejected comment: {} on content rules, because: {}",
+            submission.comment_id,
+            violation.reason()
+        );
+
+        return Err(ErrorResponse::new(
+            Status::UnprocessableEntity,
+            violation.reason(),
+        ));
+    }
+
+    Ok(())
+}
+
+pub async fn insert_comment(
+    db: &mut DbConn,
+    site: &Site,
+    page: &str,
+    submission: &CommentSubmission<'_>,
+) -> Result<(), Status> {
+    let (comment_id, name, email, text) = (
+        submission.comment_id,
+        submission.name,
+        submission.email,
+        submission.text,
+    );
+
+    // Comment already exists? (this should not happen!)
+    if query::check_comment_exists(db, comment_id).await? {
+        return Err(Status::Conflict);
+    }
+
+    // Acquire page and author identifiers
+    let page_id = query::find_or_create_page_id(db, site, page).await?;
+    let author_id = query::find_or_create_author_id(db, email, name).await?;
+
+    // Subscribe author to alerts? (store email value)
+    if submission.alerts_subscribe {
+        query::update_author_email(db, &author_id, Some(email)).await?
+    }
+
+    // Insert comment for page and author
+    query::insert_comment_for_page_id_and_author_id(
+        db,
+        comment_id,
+        text,
+        &page_id,
+        &author_id,
+        submission.reply_to,
+    )
+    .await?;
+
+    // Record mentions of earlier commenters on the same page (if any)
+    if text.contains('@') {
+        let candidates = query::list_mention_candidates_for_page_id(db, &page_id).await?;
+
+        query::insert_comment_mentions(
+            db,
+            comment_id,
+            &formatter::find_mentions(text, &candidates),
+        )
+        .await?;
+    }
+
+    // Notify admins of new comment
+    notifier::alert_of_new_comment_to_admins(site, comment_id, page, name, email, text).await;
+
+    // Trigger webhooks of new comment
+    webhook::trigger(
+        site,
+        WebhookEvent::Submitted,
+        &normalize::page_url(page)?,
+        comment_id,
+    );
+
+    Ok(())
+}
+
+fn moderation_action_label(action: &str) -> Option<&'static str> {
+    match action {
+        "approve" => Some("Approve comment"),
+        "reject" => Some("Reject comment"),
+        "pin" => Some("Pin comment"),
+        "unpin" => Some("Unpin comment"),
+        "highlight" => Some("Highlight comment"),
+        "unhighlight" => Some("Unhighlight comment"),
+        "lock" => Some("Lock comments on page"),
+        "unlock" => Some("Unlock comments on page"),
+        _ => None,
+    }
+}
+
+fn moderation_flag(action: &str) -> Option<(&'static str, bool, &'static str)> {
+    match action {
+        "pin" => Some(("pinned", true, "Comment pinned.")),
+        "unpin" => Some(("pinned", false, "Comment unpinned.")),
+        "highlight" => Some(("highlighted", true, "Comment highlighted.")),
+        "unhighlight" => Some(("highlighted", false, "Comment unhighlighted.")),
+        _ => None,
+    }
+}
+
+fn moderation_undo_action(action: &str) -> Option<&'static str> {
+    match action {
+        "pin" => Some("unpin"),
+        "unpin" => Some("pin"),
+        "highlight" => Some("unhighlight"),
+        "unhighlight" => Some("highlight"),
+        "lock" => Some("unlock"),
+        "unlock" => Some("lock"),
+        _ => None,
+    }
+}
+
+async fn moderate_comment(
+    db: &mut DbConn,
+    comment_id: &str,
+    action: &str,
+) -> Result<&'static str, Status> {
     // Resolve comment
-    let comment =
-        query::resolve_comment_status_and_reply_to_id(&mut db, comment_id, "approved").await?;
+    let comment = query::resolve_comment_status_and_reply_to_id(db, comment_id, "approved").await?;
 
     if let Some((comment_status, reply_to_id)) = comment {
+        // Resolve comment site and page (for webhooks)
+        // Notice: this must be done before processing moderation, since \
+        //   rejecting the comment removes it.
+        let comment_page = query::resolve_comment_page_and_text(db, comment_id)
+            .await?
+            .map(|(comment_site, comment_page, _)| (comment_site, comment_page));
+
+        let trigger_webhook = |event: WebhookEvent| {
+            if let Some((comment_site, ref comment_page)) = comment_page {
+                webhook::trigger(comment_site, event, comment_page, comment_id);
+            }
+        };
+
         // Process moderation
         if action == "approve" {
             if comment_status == true {
                 Ok("Comment has already been approved.")
             } else {
                 // Approve comment (mark comment as approved)
-                query::update_comment_status(&mut db, comment_id, "approved", true).await?;
+                query::update_comment_status(db, comment_id, "approved", true).await?;
 
                 // Check if should notify parent comment author of this reply?
                 // Notice: this will only notify of replies from administrators.
                 if let Some(reply_to_id) = reply_to_id {
                     notifier::alert_of_reply_comment_from_admin_if_needed(
-                        &mut db,
+                        db,
                         &reply_to_id,
                         comment_id,
                     )
                     .await;
                 }
 
+                // Notify authors mentioned in this comment (if they opted-in)
+                notifier::alert_of_mentions_in_comment_if_needed(db, comment_id).await;
+
+                trigger_webhook(WebhookEvent::Approved);
+
                 Ok("Comment approved.")
             }
         } else if action == "reject" {
             // Remove comment
-            query::remove_comment(&mut db, comment_id).await?;
+            query::remove_comment(db, comment_id).await?;
+
+            // Notice: rejecting a comment that was already approved deletes \
+            //   it from the page.
+            trigger_webhook(if comment_status {
+                WebhookEvent::Deleted
+            } else {
+                WebhookEvent::Rejected
+            });
 
             Ok("Comment rejected.")
+        } else if let Some((status_key, status_value, message)) = moderation_flag(action) {
+            // Pin or highlight comment (or revert those)
+            // Notice: flags can be set on comments that are not approved \
+            //   yet, in which case they will apply once approved.
+            query::update_comment_status(db, comment_id, status_key, status_value).await?;
+
+            trigger_webhook(WebhookEvent::Edited);
+
+            Ok(message)
+        } else if action == "lock" || action == "unlock" {
+            // Lock or unlock comments on the page of this comment
+            query::update_page_locked_for_comment_id(db, comment_id, action == "lock").await?;
+
+            Ok(if action == "lock" {
+                "Comments locked on page."
+            } else {
+                "Comments unlocked on page."
+            })
         } else {
             Err(Status::BadRequest)
         }
@@ -206,3 +860,25 @@ pub async fn get_admin_moderate_comment(
         Ok("Comment does not exist anymore.")
     }
 }
+
+async fn check_reaction_comment(
+    db: &mut DbConn,
+    site: &Site,
+    page: &str,
+    comment_id: &str,
+) -> Result<(), Status> {
+    // Ensure comment exists, is approved, and belongs to the page (so that \
+    //   the page closed check below cannot be skipped with another page)
+    let comment_page_id = query::resolve_comment_page_id(db, comment_id).await?;
+
+    if comment_page_id.is_none() || comment_page_id != query::find_page_id(db, site, page).await? {
+        return Err(Status::Gone);
+    }
+
+    // Comments are closed on page? (reactions are closed as well)
+    if query::check_page_closed(db, site, page).await? {
+        return Err(Status::Forbidden);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,226 @@
+// Bandurria
+//
+// Lightweight comment system for static websites
+// Copyright: 2025, Valerian Saliou <valerian@valeriansaliou.name>
+// License: Mozilla Public License v2.0 (MPL v2.0)
+
+use rocket::get;
+use rocket::http::hyper::header::{CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH};
+use rocket::http::{Header, RawStr, Status};
+use rocket::request::Request;
+use rocket::response::{Responder, Response, Result as ResponseResult};
+use rocket_dyn_templates::{context, Template};
+use sha2::{Digest, Sha256};
+
+use crate::helpers::site::Site;
+use crate::helpers::{formatter, normalize, query, time};
+use crate::managers::http::DbConn;
+use crate::APP_CONF;
+
+#[derive(Serialize)]
+struct FeedEntry {
+    id: String,
+    url: String,
+    author: String,
+    content: String,
+    updated: String,
+    published: String,
+}
+
+pub struct FeedResponse {
+    template: Template,
+    mime: &'static str,
+    etag: String,
+}
+
+impl<'r, 'o: 'r> Responder<'r, 'o> for FeedResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> ResponseResult<'o> {
+        // Notice: the feed has not changed if the client already holds a copy \
+        //   with the same entries (comment dates cannot tell, since comments \
+        //   get approved, rejected or removed after they were posted).
+        let not_modified = request
+            .headers()
+            .get_one(IF_NONE_MATCH.as_str())
+            .map(|if_none_match| {
+                if_none_match.split(',').any(|etag| {
+                    let etag = etag.trim();
+
+                    etag == "*" || etag.trim_start_matches("W/") == self.etag
+                })
+            })
+            .unwrap_or(false);
+
+        let mut response = if not_modified {
+            Response::build().status(Status::NotModified).finalize()
+        } else {
+            let mut response = self.template.respond_to(request)?;
+
+            response.set_header(Header::new(CONTENT_TYPE.as_str(), self.mime));
+
+            response
+        };
+
+        response.set_header(Header::new(
+            CACHE_CONTROL.as_str(),
+            format!("public, max-age={}", APP_CONF.comments.feed_max_age),
+        ));
+
+        response.set_header(Header::new(ETAG.as_str(), self.etag));
+
+        Ok(response)
+    }
+}
+
+#[get("/atom?<page>")]
+pub async fn get_atom(
+    mut db: DbConn,
+    site: &Site,
+    page: Option<&str>,
+) -> Result<FeedResponse, Status> {
+    render_feed(
+        &mut db,
+        site,
+        page,
+        "atom",
+        "application/atom+xml; charset=utf-8",
+    )
+    .await
+}
+
+#[get("/rss?<page>")]
+pub async fn get_rss(
+    mut db: DbConn,
+    site: &Site,
+    page: Option<&str>,
+) -> Result<FeedResponse, Status> {
+    render_feed(
+        &mut db,
+        site,
+        page,
+        "rss",
+        "application/rss+xml; charset=utf-8",
+    )
+    .await
+}
+
+async fn render_feed(
+    db: &mut DbConn,
+    site: &Site,
+    page: Option<&str>,
+    kind: &'static str,
+    mime: &'static str,
+) -> Result<FeedResponse, Status> {
+    let (site_name, site_url, comments_url) =
+        (site.name, site.site_url, &APP_CONF.site.comments_url);
+
+    // 1. List recent comments (for a single page, or for the whole site)
+    let (page_url, recent_comments) = match page {
+        Some(page) => {
+            let page_url = normalize::page_url(page)?;
+
+            // Notice: a page that has never been commented yields an empty \
+            //   feed, which might get filled later on.
+            let recent_comments = match query::find_page_id(db, site, &page_url).await? {
+                Some(page_id) => {
+                    query::list_recent_comments(
+                        db,
+                        site,
+                        Some(&page_id),
+                        APP_CONF.comments.feed_size as u64,
+                    )
+                    .await?
+                }
+                None => Vec::new(),
+            };
+
+            (Some(page_url), recent_comments)
+        }
+        None => (
+            None,
+            query::list_recent_comments(db, site, None, APP_CONF.comments.feed_size as u64).await?,
+        ),
+    };
+
+    // 2. Build feed entries (comments are listed most recent first)
+    let last_updated = recent_comments
+        .first()
+        .map(|recent_comment| recent_comment.created_at);
+
+    let entries: Vec<FeedEntry> = recent_comments
+        .into_iter()
+        .map(|recent_comment| FeedEntry {
+            url: format!(
+                "{}{}#comment-{}",
+                site_url, recent_comment.page, recent_comment.id
+            ),
+            id: recent_comment.id,
+            author: recent_comment.name,
+            content: if APP_CONF.comments.markdown {
+                formatter::markdown(&recent_comment.text, &[])
+            } else {
+                recent_comment
+                    .text
+                    .lines()
+                    .filter(|line| !line.is_empty())
+                    .map(|line| format!("<p>{}</p>", formatter::linkify(line, &[])))
+                    .collect()
+            },
+            updated: time::datetime_to_utc_string(&Some(recent_comment.created_at)),
+            published: time::datetime_to_rfc2822_string(&Some(recent_comment.created_at)),
+        })
+        .collect();
+
+    // 3. Render feed
+    let (feed_url, link_url) = match page_url {
+        Some(ref page_url) => (
+            format!(
+                "{}/feed/{}?page={}",
+                comments_url,
+                kind,
+                RawStr::new(page_url).percent_encode()
+            ),
+            format!("{}{}", site_url, page_url),
+        ),
+        None => (
+            format!("{}/feed/{}", comments_url, kind),
+            site_url.to_owned(),
+        ),
+    };
+
+    let updated = last_updated.unwrap_or_else(time::now_datetime);
+    let etag = make_entries_etag(&entries);
+
+    Ok(FeedResponse {
+        template: Template::render(
+            format!("feeds/{}", kind),
+            context! {
+                title: match page_url {
+                    Some(ref page_url) => format!("{}: {}", site_name, page_url),
+                    None => site_name.to_owned(),
+                },
+                feed_url,
+                link_url,
+                updated: time::datetime_to_utc_string(&Some(updated)),
+                published: time::datetime_to_rfc2822_string(&Some(updated)),
+                entries,
+            },
+        ),
+        mime,
+        etag,
+    })
+}
+
+fn make_entries_etag(entries: &[FeedEntry]) -> String {
+    // Notice: the entity tag covers the identifiers and contents of all \
+    //   entries, so that it changes whenever an entry appears or disappears.
+    let mut hasher = Sha256::new();
+
+    for entry in entries {
+        hasher.update(&entry.id);
+        hasher.update([0]);
+        hasher.update(&entry.content);
+        hasher.update([0]);
+    }
+
+    format!("\"{}\"", hex::encode(hasher.finalize()))
+}
@@ -5,5 +5,6 @@
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
 pub mod api;
+pub mod feed;
 pub mod image;
 pub mod page;
@@ -4,27 +4,84 @@
 // Copyright: 2025, Valerian Saliou <valerian@valeriansaliou.name>
 // License: Mozilla Public License v2.0 (MPL v2.0)
 
-use std::collections::HashMap;
+use std::time::Duration;
 
-use rocket::get;
+use rocket::form::{Form, FromForm};
+use rocket::http::uri::Origin;
 use rocket::http::Status;
+use rocket::response::Redirect;
+use rocket::{get, post, Responder, State};
 use rocket_dyn_templates::{context, Template};
+use uuid::Uuid;
+use validator::Validate;
 
+use super::api;
 use crate::config::config::ConfigI18N;
-use crate::helpers::query;
+use crate::helpers::locale::Locale;
+use crate::helpers::site::Site;
+use crate::helpers::{authentication, normalize, query, time};
 use crate::managers::http::DbConn;
 use crate::APP_CONF;
 
+const FORM_ATTESTATION_VALIDITY: Duration = Duration::from_secs(3600);
+const FORM_STAMP_VALIDITY: Duration = Duration::from_secs(86400);
+
+#[derive(FromForm, Validate)]
+pub struct CommentFormData {
+    #[validate(length(equal = 36))]
+    comment_id: String,
+
+    #[validate(length(max = 128))]
+    attestation: String,
+
+    #[validate(length(max = 128))]
+    answer_signature: String,
+
+    #[validate(length(min = 1, max = 80))]
+    name: String,
+
+    #[validate(email)]
+    email: String,
+
+    #[validate(length(min = 1))]
+    text: String,
+
+    #[validate(length(max = 8))]
+    answer: String,
+
+    alerts_subscribe: bool,
+    website: String,
+}
+
+#[derive(Responder)]
+pub enum CommentFormResponse {
+    Redirect(Redirect),
+    Template((Status, Template)),
+}
+
+pub struct LocalSnapshots;
+
+#[derive(Default, Serialize)]
+struct CommentFormValues<'a> {
+    name: &'a str,
+    email: &'a str,
+    text: &'a str,
+    alerts_subscribe: bool,
+}
+
 #[derive(Serialize)]
 struct CommentsOptions<'a> {
+    locale: &'a str,
     i18n: &'a ConfigI18N,
+    snapshot: bool,
     modifiers: CommentsOptionsModifiers,
-    avatar: Option<CommentsOptionsAvatar>,
+    avatar: Option<&'a CommentsOptionsAvatar>,
 }
 
 #[derive(Serialize)]
 struct CommentsOptionsModifiers {
     imprint: bool,
+    markdown: bool,
 }
 
 #[derive(Serialize)]
@@ -34,32 +91,371 @@ struct CommentsOptionsAvatar {
 }
 
 lazy_static! {
-    static ref COMMENTS_OPTIONS: CommentsOptions<'static> = CommentsOptions {
-        i18n: &APP_CONF.i18n,
-        modifiers: CommentsOptionsModifiers {
-            imprint: APP_CONF.site.show_imprint,
-        },
-        avatar: if APP_CONF.avatar.gravatar {
-            Some(CommentsOptionsAvatar {
-                avatar_endpoint: format!("{}/image/avatar", APP_CONF.site.comments_url),
-                size_image: APP_CONF.avatar.size_pixels,
-            })
-        } else {
-            None
-        }
+    static ref COMMENTS_OPTIONS_AVATAR: Option<CommentsOptionsAvatar> = if APP_CONF.avatar.gravatar
+    {
+        Some(CommentsOptionsAvatar {
+            avatar_endpoint: format!("{}/image/avatar", APP_CONF.site.comments_url),
+            size_image: APP_CONF.avatar.size_pixels,
+        })
+    } else {
+        None
     };
 }
 
-#[get("/comments?<page>")]
-pub async fn get_comments(mut db: DbConn, page: &str) -> Result<Template, Status> {
-    // Fetch all comments for page
-    let (comments, replies) = match query::find_page_id(&mut db, page).await? {
-        Some(page_id) => query::list_comments_for_page_id(&mut db, &page_id).await?,
-        None => (Vec::new(), HashMap::new()),
-    };
+#[get("/comments?<page>&<sort>&<cursor>")]
+pub async fn get_comments(
+    mut db: DbConn,
+    site: &Site,
+    locale: Locale,
+    page: &str,
+    sort: Option<&str>,
+    cursor: Option<&str>,
+) -> Result<Template, Status> {
+    // Fetch a page of comments (as a tree of threads)
+    let (comments, next_cursor) =
+        query::list_comments_for_page(&mut db, site, &locale, page, sort, cursor).await?;
+
+    // Check if comments are closed on page (the form gets replaced by a notice)
+    let closed = query::check_page_closed(&mut db, site, page).await?;
+
+    // Stamp the form with the time it got rendered at (this is checked \
+    //   against the minimum submit delay upon submitting a comment)
+    let issued_at = time::now_timestamp();
 
+    let stamp = authentication::generate_form_stamp(
+        site,
+        page,
+        issued_at,
+        issued_at + FORM_STAMP_VALIDITY.as_secs(),
+    )?;
+
+    // Render whole page, or only the next comments (when loading more)
+    Ok(Template::render(
+        if cursor.is_some() {
+            "more"
+        } else {
+            "bandurria"
+        },
+        context! {
+            comments,
+            cursor: next_cursor.map(|next_cursor| next_cursor.encode()),
+            closed,
+            stamp,
+            ctx: make_comments_options(site, &locale, false),
+        },
+    ))
+}
+
+#[get("/snapshot?<page>&<sort>&<bare>")]
+pub async fn get_snapshot(
+    mut db: DbConn,
+    site: &Site,
+    locale: Locale,
+    local: Option<&State<LocalSnapshots>>,
+    page: &str,
+    sort: Option<&str>,
+    bare: Option<bool>,
+) -> Result<Template, Status> {
+    // Ensure snapshots can be requested over HTTP (they always can from the \
+    //   render command, which requests them locally)
+    // Notice: snapshots list all comments of a page at once, which is way \
+    //   more expensive than listing a page of comments.
+    if local.is_none() && !APP_CONF.security.public_snapshots {
+        return Err(Status::NotFound);
+    }
+
+    // Fetch all comments at once (as a tree of threads)
+    // Notice: snapshots get included in static builds, where more comments \
+    //   cannot be loaded on demand, hence all threads are listed.
+    let comments = query::list_all_comments_for_page(&mut db, site, &locale, page, sort).await?;
+
+    // Check if comments are closed on page (the form gets replaced by a notice)
+    let closed = query::check_page_closed(&mut db, site, page).await?;
+
+    // Render whole page, or only the comments (without the form, to include \
+    //   in a static build)
     Ok(Template::render(
         "bandurria",
-        context! { comments, replies, ctx: &*COMMENTS_OPTIONS },
+        context! {
+            comments,
+            closed,
+            bare: bare == Some(true),
+            ctx: make_comments_options(site, &locale, true),
+        },
     ))
 }
+
+#[get("/form?<page>")]
+pub async fn get_form(
+    mut db: DbConn,
+    site: &Site,
+    locale: Locale,
+    origin: &Origin<'_>,
+    page: &str,
+) -> Result<Template, Status> {
+    // Ensure the no-JavaScript form is enabled
+    if !site.antispam.nojs_form {
+        return Err(Status::NotFound);
+    }
+
+    render_form(
+        &mut db,
+        site,
+        &locale,
+        origin,
+        page,
+        CommentFormValues::default(),
+        None,
+    )
+    .await
+}
+
+#[post("/comment?<page>", data = "<comment>")]
+pub async fn post_comment(
+    mut db: DbConn,
+    site: &Site,
+    locale: Locale,
+    origin: &Origin<'_>,
+    page: &str,
+    comment: Form<CommentFormData>,
+) -> Result<CommentFormResponse, Status> {
+    // Ensure the no-JavaScript form is enabled
+    if !site.antispam.nojs_form {
+        return Err(Status::NotFound);
+    }
+
+    // Readers are sent back to the page once their comment is submitted
+    let page_url = normalize::page_url(page)?;
+    let submitted_url = format!("{}{}#bandurria-submitted", site.site_url, page_url);
+
+    // Honeypot field is filled? (this is a bot)
+    // Notice: pretend that the comment got submitted, so that the bot does \
+    //   not learn that it got caught.
+    if !comment.website.is_empty() {
+        warn!(
+            "caught bot in honeypot for form comment: {}, discarding it",
+            comment.comment_id
+        );
+
+        return Ok(CommentFormResponse::Redirect(Redirect::to(submitted_url)));
+    }
+
+    // Submit comment (or render the form again, along with the error)
+    if let Err(error) = submit_comment(&mut db, site, page, &comment).await {
+        let values = CommentFormValues {
+            name: comment.name.trim(),
+            email: comment.email.trim(),
+            text: comment.text.trim(),
+            alerts_subscribe: comment.alerts_subscribe,
+        };
+
+        let template = render_form(
+            &mut db,
+            site,
+            &locale,
+            origin,
+            page,
+            values,
+            Some(error.reason().unwrap_or("")),
+        )
+        .await?;
+
+        return Ok(CommentFormResponse::Template((error.status(), template)));
+    }
+
+    Ok(CommentFormResponse::Redirect(Redirect::to(submitted_url)))
+}
+
+async fn submit_comment(
+    db: &mut DbConn,
+    site: &Site,
+    page: &str,
+    comment: &CommentFormData,
+) -> Result<(), api::ErrorResponse> {
+    // Data is invalid?
+    if comment.validate().is_err() {
+        return Err(Status::UnprocessableEntity.into());
+    }
+
+    // Read raw input data
+    let comment_id = comment.comment_id.as_str();
+
+    // Clean input data
+    // Notice: the form does not support replies, only top-level comments.
+    let submission = api::CommentSubmission {
+        comment_id,
+        name: comment.name.trim(),
+        email: comment.email.trim(),
+        text: comment.text.trim(),
+        reply_to: &None,
+        alerts_subscribe: comment.alerts_subscribe,
+    };
+
+    // Check comment data
+    api::check_comment(db, site, page, &submission).await?;
+
+    // Verify attestation
+    // Notice: an expired form gets rendered again along with a new question, \
+    //   hence why it is reported as a wrong answer.
+    let (issued_at, expire_at) =
+        authentication::verify_form_attestation(site, page, comment_id, &comment.attestation)
+            .ok_or(api::ErrorResponse::new(
+                Status::Unauthorized,
+                "question_wrong",
+            ))?;
+
+    // Comment was submitted too fast? (readers without JavaScript have to \
+    //   type their comment in the form that was just loaded)
+    if time::now_timestamp() < issued_at + site.antispam.nojs_submit_delay_minimum {
+        warn!(
+            "rejected form comment: {} because it was submitted too fast",
+            comment_id
+        );
+
+        return Err(api::ErrorResponse::new(
+            Status::TooManyRequests,
+            "submit_too_fast",
+        ));
+    }
+
+    // Attestation was already consumed? (it can only be used once)
+    if query::check_attestation_consumed(db, comment_id).await? {
+        warn!(
+            "rejected replayed attestation for form comment: {}",
+            comment_id
+        );
+
+        return Err(Status::Conflict.into());
+    }
+
+    // Verify answer to the question
+    // Notice: an answer that is not a number cannot be right. A wrong answer \
+    //   consumes the attestation, so that answers cannot be guessed by \
+    //   submitting the same form over and over (a new question gets asked).
+    let is_answer_right = comment
+        .answer
+        .trim()
+        .parse::<u8>()
+        .map(|answer| {
+            authentication::verify_form_answer_signature(
+                comment_id,
+                answer,
+                &comment.answer_signature,
+            )
+        })
+        .unwrap_or(false);
+
+    if !is_answer_right {
+        query::consume_attestation(db, comment_id, expire_at).await?;
+
+        return Err(api::ErrorResponse::new(
+            Status::Unauthorized,
+            "question_wrong",
+        ));
+    }
+
+    // Consume attestation (it can only be used once)
+    if !query::consume_attestation(db, comment_id, expire_at).await? {
+        warn!(
+            "rejected replayed attestation for form comment: {}",
+            comment_id
+        );
+
+        return Err(Status::Conflict.into());
+    }
+
+    // Insert comment (and notify of it)
+    api::insert_comment(db, site, page, &submission).await?;
+
+    Ok(())
+}
+
+async fn render_form(
+    db: &mut DbConn,
+    site: &Site,
+    locale: &Locale,
+    origin: &Origin<'_>,
+    page: &str,
+    values: CommentFormValues<'_>,
+    error: Option<&str>,
+) -> Result<Template, Status> {
+    // Check if comments are closed on page (the form gets replaced by a notice)
+    let closed = query::check_page_closed(db, site, page).await?;
+
+    // Generate a future comment ID, and a question to ask about it
+    // Notice: the comment ID is random, so its bytes are used to pick the \
+    //   numbers of the question.
+    let comment_uuid = Uuid::new_v4();
+    let comment_id = comment_uuid.to_string();
+
+    let (number_left, number_right) = (
+        comment_uuid.as_bytes()[0] % 9 + 1,
+        comment_uuid.as_bytes()[1] % 9 + 1,
+    );
+
+    // Sign the answer along with the comment ID, so that it can be verified
+    let issued_at = time::now_timestamp();
+    let expire_at = issued_at + FORM_ATTESTATION_VALIDITY.as_secs();
+
+    let attestation =
+        authentication::generate_form_attestation(site, page, &comment_id, issued_at, expire_at)?;
+
+    let answer_signature =
+        authentication::generate_form_answer_signature(&comment_id, number_left + number_right)?;
+
+    // Submit the form with the same query (ie. page, site and locale)
+    let action = format!(
+        "{}/page/comment/?{}",
+        APP_CONF.site.comments_url,
+        origin.query().map(|query| query.as_str()).unwrap_or("")
+    );
+
+    Ok(Template::render(
+        "form",
+        context! {
+            closed,
+            form: context! {
+                action,
+                comment_id,
+                attestation,
+                answer_signature,
+                question: locale.i18n.field_nojs_question.replace(
+                    "{question}",
+                    &format!("{number_left} + {number_right}"),
+                ),
+                error: error.map(|error| form_error_message(locale.i18n, error)),
+                values,
+            },
+            ctx: make_comments_options(site, locale, false),
+        },
+    ))
+}
+
+fn make_comments_options<'a>(
+    site: &Site,
+    locale: &'a Locale,
+    snapshot: bool,
+) -> CommentsOptions<'a> {
+    CommentsOptions {
+        locale: locale.key,
+        i18n: locale.i18n,
+        snapshot,
+        modifiers: CommentsOptionsModifiers {
+            imprint: site.show_imprint,
+            markdown: APP_CONF.comments.markdown,
+        },
+        avatar: COMMENTS_OPTIONS_AVATAR.as_ref(),
+    }
+}
+
+fn form_error_message<'a>(i18n: &'a ConfigI18N, reason: &str) -> &'a str {
+    match reason {
+        "question_wrong" => &i18n.banner_submiterror_question_wrong,
+        "comments_closed" => &i18n.banner_comments_closed,
+        "text_too_short" => &i18n.banner_submiterror_text_too_short,
+        "text_too_long" => &i18n.banner_submiterror_text_too_long,
+        "links_too_many" => &i18n.banner_submiterror_links_too_many,
+        "text_banned" => &i18n.banner_submiterror_text_banned,
+        _ => &i18n.banner_submiterror,
+    }
+}